@@ -0,0 +1,319 @@
+use crate::bitstream::{BitReader, BitWriter};
+use crate::huffman::DecodeError;
+
+// 256 byte leaves, 255 internal nodes joining them, the NYT leaf and its parent
+const MAX_NODES: usize = 2 * 257 - 1;
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    weight: u64,
+    parent: Option<usize>,
+    left: Option<usize>,
+    right: Option<usize>,
+    symbol: Option<u8>,
+    // implicit number of the node, the root has the biggest one
+    order: usize,
+}
+
+/// Huffman tree updated after every symbol (algorithm FGK by Faller, Gallager and Knuth).
+///
+/// The tree starts as a single NYT ("not yet transmitted") leaf of weight 0. A symbol seen for
+/// the first time is sent as the code of NYT followed by its 8 raw bits, and NYT is split into
+/// a new NYT and a leaf of that symbol. After every symbol the weights on the path from its leaf
+/// to the root are incremented.
+///
+/// The tree keeps the sibling property: ordered by their implicit numbers, the weights of the
+/// nodes never decrease and siblings are adjacent, which makes it a Huffman tree of the counts
+/// seen so far. Before incrementing a node it is swapped with the node of the biggest number
+/// among those of the same weight (the block leader), which preserves the property.
+struct AdaptiveTree {
+    nodes: Vec<Node>,
+    // node index by implicit number
+    by_order: Vec<usize>,
+    // leaf index by symbol
+    leaves: Vec<Option<usize>>,
+    nyt: usize,
+    root: usize,
+}
+
+impl AdaptiveTree {
+    fn new() -> Self {
+        let root = Node {
+            weight: 0,
+            parent: None,
+            left: None,
+            right: None,
+            symbol: None,
+            order: MAX_NODES - 1,
+        };
+        let mut by_order = vec![usize::MAX; MAX_NODES];
+        by_order[MAX_NODES - 1] = 0;
+        AdaptiveTree {
+            nodes: vec![root],
+            by_order,
+            leaves: vec![None; 256],
+            nyt: 0,
+            root: 0,
+        }
+    }
+
+    fn is_leaf(&self, node: usize) -> bool {
+        self.nodes[node].left.is_none()
+    }
+
+    // writes the bits of the path from the root to the node
+    fn write_path(&self, node: usize, writer: &mut BitWriter) {
+        let mut path = Vec::new();
+        let mut current = node;
+        while let Some(parent) = self.nodes[current].parent {
+            path.push(self.nodes[parent].right == Some(current));
+            current = parent;
+        }
+        for bit in path.iter().rev() {
+            writer.write_bit(*bit);
+        }
+    }
+
+    fn encode(&mut self, symbol: u8, writer: &mut BitWriter) {
+        match self.leaves[symbol as usize] {
+            Some(leaf) => self.write_path(leaf, writer),
+            None => {
+                self.write_path(self.nyt, writer);
+                writer.write_bits(symbol as u64, 8);
+            }
+        }
+        self.update(symbol);
+    }
+
+    fn decode(&mut self, reader: &mut BitReader) -> Result<u8, DecodeError> {
+        let mut node = self.root;
+        while !self.is_leaf(node) {
+            let bit = reader.read_bit().ok_or(DecodeError::UnexpectedEnd)?;
+            node = if bit {
+                self.nodes[node].right.unwrap()
+            } else {
+                self.nodes[node].left.unwrap()
+            };
+        }
+
+        let symbol = if node == self.nyt {
+            let symbol = reader.read_bits(8).ok_or(DecodeError::UnexpectedEnd)? as u8;
+            // a symbol is sent raw only once, anything else is corrupted data
+            if self.leaves[symbol as usize].is_some() {
+                return Err(DecodeError::InvalidCodeTree);
+            }
+            symbol
+        } else {
+            self.nodes[node].symbol.unwrap()
+        };
+        self.update(symbol);
+        Ok(symbol)
+    }
+
+    fn update(&mut self, symbol: u8) {
+        let mut node = match self.leaves[symbol as usize] {
+            Some(leaf) => leaf,
+            None => self.split_nyt(symbol),
+        };
+
+        loop {
+            let leader = self.block_leader(node);
+            if leader != node && Some(leader) != self.nodes[node].parent {
+                self.swap(node, leader);
+            }
+            self.nodes[node].weight += 1;
+            match self.nodes[node].parent {
+                Some(parent) => node = parent,
+                None => break,
+            }
+        }
+    }
+
+    // NYT becomes an internal node with a new NYT as its left child and the leaf of the symbol
+    // as its right child, returns the new leaf
+    fn split_nyt(&mut self, symbol: u8) -> usize {
+        let parent = self.nyt;
+        let order = self.nodes[parent].order;
+
+        let leaf = self.nodes.len();
+        self.nodes.push(Node {
+            weight: 0,
+            parent: Some(parent),
+            left: None,
+            right: None,
+            symbol: Some(symbol),
+            order: order - 1,
+        });
+        self.by_order[order - 1] = leaf;
+
+        let nyt = self.nodes.len();
+        self.nodes.push(Node {
+            weight: 0,
+            parent: Some(parent),
+            left: None,
+            right: None,
+            symbol: None,
+            order: order - 2,
+        });
+        self.by_order[order - 2] = nyt;
+
+        self.nodes[parent].left = Some(nyt);
+        self.nodes[parent].right = Some(leaf);
+        self.nyt = nyt;
+        self.leaves[symbol as usize] = Some(leaf);
+        leaf
+    }
+
+    // the node of the biggest number among the nodes of the same weight
+    fn block_leader(&self, node: usize) -> usize {
+        let weight = self.nodes[node].weight;
+        let mut leader = node;
+        for order in self.nodes[node].order + 1..MAX_NODES {
+            let candidate = self.by_order[order];
+            if self.nodes[candidate].weight != weight {
+                break;
+            }
+            leader = candidate;
+        }
+        leader
+    }
+
+    // exchanges the positions of two subtrees, neither of which contains the other
+    fn swap(&mut self, a: usize, b: usize) {
+        let parent_a = self.nodes[a].parent.unwrap();
+        let parent_b = self.nodes[b].parent.unwrap();
+
+        if parent_a == parent_b {
+            let parent = &mut self.nodes[parent_a];
+            std::mem::swap(&mut parent.left, &mut parent.right);
+        } else {
+            let slot_a = if self.nodes[parent_a].left == Some(a) {
+                &mut self.nodes[parent_a].left
+            } else {
+                &mut self.nodes[parent_a].right
+            };
+            *slot_a = Some(b);
+            let slot_b = if self.nodes[parent_b].left == Some(b) {
+                &mut self.nodes[parent_b].left
+            } else {
+                &mut self.nodes[parent_b].right
+            };
+            *slot_b = Some(a);
+            self.nodes[a].parent = Some(parent_b);
+            self.nodes[b].parent = Some(parent_a);
+        }
+
+        let (order_a, order_b) = (self.nodes[a].order, self.nodes[b].order);
+        self.nodes[a].order = order_b;
+        self.nodes[b].order = order_a;
+        self.by_order[order_a] = b;
+        self.by_order[order_b] = a;
+    }
+}
+
+/// Compresses `data` in a single pass with an adaptive Huffman code of its bytes.
+///
+/// Layout of the output, packed by `BitWriter`:
+/// - number of bytes of `data`, 64 bits
+/// - the codewords of the bytes of `data`, each in the tree updated by the preceding bytes
+pub fn encode(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::with_capacity(data.len() / 2 + 8);
+    writer.write_bits(data.len() as u64, 64);
+
+    let mut tree = AdaptiveTree::new();
+    for byte in data {
+        tree.encode(*byte, &mut writer);
+    }
+
+    writer.finish()
+}
+
+/// Restores the data compressed by `encode`, rebuilding the same tree symbol by symbol.
+pub fn decode(encoded: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let mut reader = BitReader::new(encoded);
+    let len = reader.read_bits(64).ok_or(DecodeError::UnexpectedEnd)? as usize;
+
+    let mut tree = AdaptiveTree::new();
+    // don't trust the length of corrupted data for the allocation
+    let mut data = Vec::with_capacity(std::cmp::min(len, reader.remaining()));
+    while data.len() < len {
+        data.push(tree.decode(&mut reader)?);
+    }
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::huffman;
+    use rand::{thread_rng, Rng};
+
+    #[test]
+    fn test_encode_decode() {
+        for data in [
+            &b""[..],
+            b"a",
+            b"aaaaaaaa",
+            b"abracadabra",
+            b"this is an example of a huffman tree",
+        ] {
+            assert_eq!(decode(&encode(data)).unwrap(), data);
+        }
+
+        // "a" is sent raw, then the tree is NYT with codeword 0 and "a" with codeword 1
+        assert_eq!(encode(b"aa").len(), 8 + 2);
+
+        let mut rng = thread_rng();
+        let data: Vec<u8> = (0..20_000).map(|_| rng.gen_range(0..=255)).collect();
+        assert_eq!(decode(&encode(&data)).unwrap(), data);
+
+        // a single pass costs little over the static code which stores the whole tree
+        let data: Vec<u8> = (0..20_000)
+            .map(|_| rng.gen_range(0..16) * rng.gen_range(0..2))
+            .collect();
+        let encoded = encode(&data);
+        assert_eq!(decode(&encoded).unwrap(), data);
+        assert!(encoded.len() <= huffman::encode(&data).len() * 105 / 100);
+    }
+
+    #[test]
+    fn test_sibling_property() {
+        let mut rng = thread_rng();
+        let mut tree = AdaptiveTree::new();
+        let mut writer = BitWriter::new();
+        for _ in 0..5000 {
+            tree.encode(rng.gen_range(0..40) / rng.gen_range(1..8), &mut writer);
+
+            // nodes ordered by their numbers have non decreasing weights, and every internal
+            // node weighs as much as its children
+            let used = MAX_NODES - tree.nodes.len()..MAX_NODES;
+            let weights: Vec<u64> = used
+                .map(|order| tree.nodes[tree.by_order[order]].weight)
+                .collect();
+            assert!(weights.windows(2).all(|pair| pair[0] <= pair[1]));
+            for node in &tree.nodes {
+                if let (Some(left), Some(right)) = (node.left, node.right) {
+                    assert_eq!(
+                        node.weight,
+                        tree.nodes[left].weight + tree.nodes[right].weight
+                    );
+                    assert_eq!(tree.nodes[left].order + 1, tree.nodes[right].order);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(decode(&[0, 0, 0]), Err(DecodeError::UnexpectedEnd));
+        let encoded = encode(b"abracadabra");
+        assert_eq!(
+            decode(&encoded[..encoded.len() - 1]),
+            Err(DecodeError::UnexpectedEnd)
+        );
+        // two symbols, both "a" sent raw
+        let corrupted = [0, 0, 0, 0, 0, 0, 0, 2, b'a', 0b0011_0000, 0b1000_0000];
+        assert_eq!(decode(&corrupted), Err(DecodeError::InvalidCodeTree));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::bitstream::{BitReader, BitWriter};
+use crate::entropy::{EntropyCoder, FrequencyModel, TOTAL};
+use crate::huffman::{DecodeError, MAX_DECODED_LEN};
+
+const PRECISION: u32 = 32;
+const FULL: u64 = 1 << PRECISION;
+const HALF: u64 = FULL / 2;
+const QUARTER: u64 = FULL / 4;
+
+/// Arithmetic coder with 32 bit integer intervals (Witten, Neal and Cleary).
+///
+/// Every symbol narrows the interval `low..=high` to the part of its probability. Once both
+/// ends share the leading bit it is written out and the interval doubled; an interval straddling
+/// the middle but within the middle half is doubled around the center, postponing the bit until
+/// the side is known. After renormalization the interval spans more than a quarter of the
+/// range, so symbols of frequency 1 out of `TOTAL` still get a part of it.
+pub struct ArithmeticCoder;
+
+// writes the bit and the postponed opposite bits
+fn write_bit_with_pending(writer: &mut BitWriter, bit: bool, pending: &mut u64) {
+    writer.write_bit(bit);
+    for _ in 0..*pending {
+        writer.write_bit(!bit);
+    }
+    *pending = 0;
+}
+
+// the bits after the end of the data are zeros, like the padding of the last byte, the decoder
+// reads at most PRECISION bits further than the encoder wrote
+fn read_bit(reader: &mut BitReader, past_end: &mut u32) -> Result<u64, DecodeError> {
+    match reader.read_bit() {
+        Some(bit) => Ok(bit as u64),
+        None if *past_end < PRECISION => {
+            *past_end += 1;
+            Ok(0)
+        }
+        None => Err(DecodeError::UnexpectedEnd),
+    }
+}
+
+impl EntropyCoder for ArithmeticCoder {
+    fn name(&self) -> &'static str {
+        "arithmetic"
+    }
+
+    fn encode(&self, model: &FrequencyModel, data: &[u8]) -> Vec<u8> {
+        let mut writer = BitWriter::with_capacity(data.len() / 2);
+        if data.is_empty() {
+            return writer.finish();
+        }
+
+        let (mut low, mut high) = (0u64, FULL - 1);
+        let mut pending = 0;
+        for byte in data {
+            let frequency = model.frequency(*byte) as u64;
+            assert!(frequency > 0, "Byte {} isn't in the model", byte);
+            let cumulative = model.cumulative(*byte) as u64;
+            let range = high - low + 1;
+            high = low + range * (cumulative + frequency) / TOTAL as u64 - 1;
+            low += range * cumulative / TOTAL as u64;
+
+            loop {
+                if high < HALF {
+                    write_bit_with_pending(&mut writer, false, &mut pending);
+                } else if low >= HALF {
+                    write_bit_with_pending(&mut writer, true, &mut pending);
+                    low -= HALF;
+                    high -= HALF;
+                } else if low >= QUARTER && high < HALF + QUARTER {
+                    pending += 1;
+                    low -= QUARTER;
+                    high -= QUARTER;
+                } else {
+                    break;
+                }
+                low *= 2;
+                high = high * 2 + 1;
+            }
+        }
+
+        // two more bits select a quarter within the final interval
+        pending += 1;
+        write_bit_with_pending(&mut writer, low >= QUARTER, &mut pending);
+        writer.finish()
+    }
+
+    fn decode(
+        &self,
+        model: &FrequencyModel,
+        encoded: &[u8],
+        len: usize,
+    ) -> Result<Vec<u8>, DecodeError> {
+        // symbols of a single symbol model take no bits, so the input doesn't bound the length
+        if len > MAX_DECODED_LEN {
+            return Err(DecodeError::TooLong);
+        }
+        let mut data = Vec::with_capacity(std::cmp::min(len, encoded.len() * 8));
+        if len == 0 {
+            return Ok(data);
+        }
+
+        let mut reader = BitReader::new(encoded);
+        let mut past_end = 0;
+        let mut value = 0u64;
+        for _ in 0..PRECISION {
+            value = value * 2 + read_bit(&mut reader, &mut past_end)?;
+        }
+
+        let (mut low, mut high) = (0u64, FULL - 1);
+        while data.len() < len {
+            let range = high - low + 1;
+            let slot = ((value - low + 1) * TOTAL as u64 - 1) / range;
+            let byte = model.symbol(slot as u32);
+            let frequency = model.frequency(byte) as u64;
+            let cumulative = model.cumulative(byte) as u64;
+            high = low + range * (cumulative + frequency) / TOTAL as u64 - 1;
+            low += range * cumulative / TOTAL as u64;
+            data.push(byte);
+
+            loop {
+                if high < HALF {
+                } else if low >= HALF {
+                    value -= HALF;
+                    low -= HALF;
+                    high -= HALF;
+                } else if low >= QUARTER && high < HALF + QUARTER {
+                    value -= QUARTER;
+                    low -= QUARTER;
+                    high -= QUARTER;
+                } else {
+                    break;
+                }
+                low *= 2;
+                high = high * 2 + 1;
+                value = value * 2 + read_bit(&mut reader, &mut past_end)?;
+            }
+        }
+
+        Ok(data)
+    }
+}
@@ -1,10 +1,18 @@
-use algorithms::huffman::BinaryTree;
-use algorithms::{graph, heap, huffman, merge_sort, mwis, quick_sort, selection_sort};
-use std::cmp::Ordering;
-use std::collections::{HashMap, HashSet, VecDeque};
+use algorithms::arithmetic_coding::ArithmeticCoder;
+use algorithms::entropy::{self, EntropyCoder};
+use algorithms::huffman::HuffmanCoder;
+use algorithms::rans::RansCoder;
+use algorithms::{
+    diff, graph, heap, huffman, huffman_container, huffman_table, knapsack, merge_sort, mwis,
+    quick_sort, scheduling, selection_sort,
+};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
 use std::thread::Builder;
 use std::time::{Duration, Instant};
-use std::{cmp, fs};
+use std::{cmp, env, fs, process};
 
 use petgraph::algo::{dijkstra, min_spanning_tree, toposort};
 use petgraph::data::FromElements;
@@ -15,9 +23,200 @@ use petgraph::unionfind::UnionFind;
 use petgraph::Graph;
 
 use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::{thread_rng, Rng};
 
 fn main() {
+    let args: Vec<String> = env::args().collect();
+    let result = match args.get(1).map(String::as_str) {
+        Some("compress") if args.len() == 4 => compress_file(&args[2], &args[3]),
+        Some("decompress") if args.len() == 4 => decompress_file(&args[2], &args[3]),
+        Some("compare") if args.len() == 3 => compare_coders(&args[2]),
+        Some("bench-decode") if args.len() <= 3 => bench_huffman_decode(args.get(2)),
+        Some("knapsack") if args.len() == 3 => compute_knapsack(&args[2]),
+        Some("diff") if args.len() == 4 => diff_files(&args[2], &args[3]),
+        Some("compress") | Some("decompress") | Some("compare") | Some("knapsack")
+        | Some("diff") | Some("bench-decode") => {
+            eprintln!("usage: {} compress|decompress <input> <output>", args[0]);
+            eprintln!("       {} compare <input>", args[0]);
+            eprintln!("       {} bench-decode [input]", args[0]);
+            eprintln!("       {} knapsack <input>", args[0]);
+            eprintln!("       {} diff <old> <new>", args[0]);
+            process::exit(2);
+        }
+        _ => {
+            sort_benchmark();
+            Ok(())
+        }
+    };
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
+
+fn compress_file(input: &str, output: &str) -> Result<(), Box<dyn Error>> {
+    let mut reader = BufReader::new(File::open(input)?);
+    let mut writer = BufWriter::new(File::create(output)?);
+    let written = huffman_container::compress(&mut reader, &mut writer)?;
+    writer.flush()?;
+    let original = fs::metadata(input)?.len();
+    println!(
+        "{}: {} -> {} bytes ({:.1}%)",
+        input,
+        original,
+        written,
+        written as f64 * 100.0 / cmp::max(original, 1) as f64
+    );
+    Ok(())
+}
+
+fn decompress_file(input: &str, output: &str) -> Result<(), Box<dyn Error>> {
+    let mut reader = BufReader::new(File::open(input)?);
+    let mut writer = BufWriter::new(File::create(output)?);
+    let result = huffman_container::decompress(&mut reader, &mut writer).and_then(|written| {
+        writer.flush()?;
+        Ok(written)
+    });
+    if result.is_err() {
+        // don't leave a partial or corrupted file behind
+        drop(writer);
+        fs::remove_file(output)?;
+    }
+    println!("{}: {} bytes", output, result?);
+    Ok(())
+}
+
+fn compare_coders(input: &str) -> Result<(), Box<dyn Error>> {
+    let data = fs::read(input)?;
+    let coders: [&dyn EntropyCoder; 3] = [&HuffmanCoder, &ArithmeticCoder, &RansCoder];
+    println!(
+        "{}: {} bytes, entropy {:.4} bits per byte",
+        input,
+        data.len(),
+        entropy::FrequencyModel::from_data(&data).entropy()
+    );
+    for report in entropy::compare(&coders, &data) {
+        println!(
+            "{:>10}: {} bytes, {:.4} bits per byte, encode {:?}, decode {:?}",
+            report.name,
+            report.encoded_len,
+            report.bits_per_symbol,
+            report.encode_time,
+            report.decode_time
+        );
+    }
+    Ok(())
+}
+
+// prints the unified diff of the files and exits with 1 if they differ, like diff -u
+fn diff_files(old: &str, new: &str) -> Result<(), Box<dyn Error>> {
+    let output = diff::unified_diff(
+        &fs::read_to_string(old)?,
+        &fs::read_to_string(new)?,
+        old,
+        new,
+        3,
+    );
+    if !output.is_empty() {
+        print!("{}", output);
+        process::exit(1);
+    }
+    Ok(())
+}
+
+// solves the knapsack of the course format: "capacity number_of_items" on the first line, then
+// "value weight" of an item per line
+fn compute_knapsack(input: &str) -> Result<(), Box<dyn Error>> {
+    let contents = fs::read_to_string(input)?;
+    let mut lines = contents.lines();
+    let metainfo: Vec<u64> = lines
+        .next()
+        .ok_or("missing the capacity and number of items")?
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<_, _>>()?;
+    let [capacity, number_of_items] = metainfo[..] else {
+        return Err("expected the capacity and number of items on the first line".into());
+    };
+    let mut items = Vec::new();
+    for line in lines.filter(|line| !line.trim().is_empty()) {
+        let numbers: Vec<u64> = line
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<_, _>>()?;
+        let [value, weight] = numbers[..] else {
+            return Err(format!("expected a value and a weight, got {:?}", line).into());
+        };
+        items.push(knapsack::Item { value, weight });
+    }
+    if items.len() as u64 != number_of_items {
+        return Err(format!("expected {} items, got {}", number_of_items, items.len()).into());
+    }
+
+    // a table per capacity for knapsack.txt, too big for the 2 million of knapsack_big.txt
+    let start = Instant::now();
+    let packing = if capacity <= 100_000 {
+        knapsack::knapsack_01(&items, capacity)
+    } else {
+        knapsack::knapsack_01_sparse(&items, capacity)
+    };
+    println!("Computing the knapsack took: {:?}", start.elapsed());
+    println!(
+        "Capacity {}, {} items: value {}, weight {}, {} items packed",
+        capacity,
+        items.len(),
+        packing.value,
+        packing.weight,
+        packing.items.len()
+    );
+    Ok(())
+}
+
+// times the tree walking and the table driven decoders of the same encoded input, by default
+// 8 MB of bytes of geometrically decreasing frequencies
+fn bench_huffman_decode(input: Option<&String>) -> Result<(), Box<dyn Error>> {
+    let data = match input {
+        Some(input) => fs::read(input)?,
+        None => {
+            let mut rng = thread_rng();
+            (0..8 << 20)
+                .map(|_| (rng.gen_range(0u32..1 << 24) as f64).log2() as u8 * 7)
+                .collect()
+        }
+    };
+    let encoded = huffman::encode(&data);
+    let megabytes = data.len() as f64 / (1 << 20) as f64;
+    println!(
+        "{:.1} MB encoded into {:.1} MB",
+        megabytes,
+        encoded.len() as f64 / (1 << 20) as f64
+    );
+
+    let start = Instant::now();
+    let decoded = huffman::decode(&encoded)?;
+    let tree_time = start.elapsed();
+    assert!(decoded == data, "the tree decoder didn't restore the data");
+    let start = Instant::now();
+    let decoded = huffman_table::decode(&encoded)?;
+    let table_time = start.elapsed();
+    assert!(decoded == data, "the table decoder didn't restore the data");
+
+    for (name, time) in [("tree", tree_time), ("table", table_time)] {
+        println!(
+            "{:>5}: {:?}, {:.1} MB/s",
+            name,
+            time,
+            megabytes / time.as_secs_f64()
+        );
+    }
+    println!(
+        "table decoding is {:.1}x faster",
+        tree_time.as_secs_f64() / table_time.as_secs_f64()
+    );
+    Ok(())
+}
+
+fn sort_benchmark() {
     let mut vec: Vec<i64> = (1..=1_000_000).collect();
     vec.shuffle(&mut thread_rng());
     let start = Instant::now();
@@ -142,9 +341,9 @@ fn compute_weight_independent_set() {
         (517, 0b00000010),
         (997, 0b00000001),
     ];
+    println!("Maximum weight: {}", mwis.total_weight);
     for (v_index, bit_mask) in question_vertices {
-        let weight = weights[v_index - 1];
-        if mwis.contains(&weight) {
+        if mwis.contains(v_index - 1) {
             coursera_answer ^= bit_mask;
         }
     }
@@ -167,60 +366,23 @@ fn compute_huffman() {
     assert_eq!(symbol_freqs.len(), number_of_symbols);
 
     let start = Instant::now();
-    let mut code_tree = huffman::compute_code_tree(&symbol_freqs);
+    let code_tree = huffman::compute_code_tree(&symbol_freqs);
     let duration = start.elapsed();
     println!("Computing Huffman codes took: {:?}", duration);
 
     let start = Instant::now();
-    let (min, max) = huffman_longest_codeword_search(code_tree);
+    let stats = code_tree.stats();
     let duration = start.elapsed();
-    println!("Computing min and max codeword length took: {:?}", duration);
-
-    println!("min={}, max={}", min, max);
-}
-
-fn huffman_longest_codeword_search(tree: BinaryTree<usize>) -> (usize, usize) {
-    let mut explored = HashSet::<usize>::new();
-    explored.insert(tree.value);
-
-    let mut code_lengths = HashMap::<usize, usize>::new();
-    code_lengths.insert(tree.value, 0);
-
-    // create a queue with vertex "s" as a starting point
-    let mut deq = VecDeque::from([tree]);
-
-    let mut min_codeword = usize::MAX;
-    // traverse the graph starting from "s"
-    while !deq.is_empty() {
-        let tree = deq.pop_front().unwrap();
-        let parent_code_length = code_lengths.get(&tree.value).unwrap().clone();
-        // println!("parent_code_length={}", parent_code_length);
-
-        // end leaf of a tree, so we need to record its codeword length
-        if tree.left == None && tree.right == None {
-            min_codeword = cmp::min(min_codeword, parent_code_length);
-        }
-
-        // a vertex might not have adjacent vertices
-        if let Some(node) = tree.left {
-            if !explored.contains(&node.value) {
-                code_lengths.insert(node.value.clone(), parent_code_length + 1);
-                explored.insert(node.value);
-                deq.push_back(*node);
-            }
-        }
-        if let Some(node) = tree.right {
-            if !explored.contains(&node.value) {
-                code_lengths.insert(node.value.clone(), parent_code_length + 1);
-                explored.insert(node.value);
-                deq.push_back(*node);
-            }
-        }
-    }
+    println!("Computing code tree statistics took: {:?}", duration);
 
-    let mut vec: Vec<usize> = code_lengths.into_values().collect();
-    vec.sort();
-    (min_codeword, vec.pop().unwrap())
+    println!(
+        "min={}, max={}",
+        stats.min_codeword_len, stats.max_codeword_len
+    );
+    println!(
+        "average={:.3}, bits per symbol={:.3}, entropy={:.3}, efficiency={:.3}",
+        stats.average_codeword_len, stats.weighted_average_len, stats.entropy, stats.efficiency
+    );
 }
 
 fn compute_clusters_big() {
@@ -410,45 +572,28 @@ fn compute_job_schedule() {
     let contents = fs::read_to_string("_642c2ce8f3abe387bdff636d708cdb26_jobs.txt")
         .expect("Something went wrong reading the file");
     let number_of_jobs: usize = contents.lines().next().unwrap().parse::<usize>().unwrap();
-    let mut jobs: Vec<(i64, i64)> = contents
+    let jobs: Vec<scheduling::Job> = contents
         .lines()
         .skip(1)
         .map(|line| {
             let job_info: Vec<&str> = line.split_whitespace().collect();
-            let job_weight: i64 = job_info[0].parse().unwrap();
-            let job_length: i64 = job_info[1].parse().unwrap();
-            (job_weight, job_length)
+            scheduling::Job {
+                weight: job_info[0].parse().unwrap(),
+                length: job_info[1].parse().unwrap(),
+            }
         })
         .collect();
-    if jobs.is_empty() {
-        println!("No numbers were parsed");
-    }
-
-    jobs.sort_by(|a, b| {
-        let a_order: f64 = (a.0 as f64) / (a.1 as f64);
-        let b_order: f64 = (b.0 as f64) / (b.1 as f64);
-        if a_order <= b_order {
-            Ordering::Greater
-        } else {
-            Ordering::Less
-        }
-    });
+    assert!(jobs.len() == number_of_jobs);
 
-    let mut sum_of_weighted_completion_times = 0;
-    let mut completion_time = 0;
-    for job in &jobs {
-        completion_time += job.1;
-        sum_of_weighted_completion_times += job.0 * completion_time;
+    // the assignment breaks ties of the difference by weight, ties of the ratio don't matter
+    for rule in [scheduling::Rule::Difference, scheduling::Rule::Ratio] {
+        let order = scheduling::greedy_order(&jobs, rule, scheduling::TieBreak::HigherWeight);
+        println!(
+            "{:?}: sum_of_weighted_completion_times={}",
+            rule,
+            scheduling::weighted_completion_time(&jobs, &order)
+        );
     }
-
-    assert!(jobs.len() == number_of_jobs);
-    println!("jobs[0]={:?}", jobs[0]);
-    println!("jobs[last]={:?}", jobs[jobs.len() - 1]);
-    println!("jobs[..5]={:?}", &jobs[..20]);
-    println!(
-        "sum_of_weighted_completion_times={}",
-        sum_of_weighted_completion_times
-    );
 }
 
 fn compute_two_sum() {
@@ -507,9 +652,14 @@ fn compute_median_maintainance() {
     }
 
     let start = Instant::now();
-    let medians = heap::compute_median_stream(&mut numbers);
+    let medians = heap::compute_median_stream(&numbers);
     let duration = start.elapsed();
     println!("Computing stream of medians: {:?}", duration);
+
+    println!("medians.len()={}", medians.len());
+    let median_sum: u64 = medians.iter().sum();
+    println!("median_sum={}", median_sum);
+    println!("median_sum % 10000={}", median_sum % 10000);
 }
 
 fn selection_sort() {
@@ -0,0 +1,208 @@
+/// Packs bits into bytes, most significant bit first.
+pub struct BitWriter {
+    bytes: Vec<u8>,
+    // bits of the last, partially filled byte
+    current: u8,
+    used: u32,
+    // number of bytes already returned by take_bytes
+    taken: usize,
+}
+
+impl BitWriter {
+    pub fn new() -> Self {
+        BitWriter {
+            bytes: Vec::new(),
+            current: 0,
+            used: 0,
+            taken: 0,
+        }
+    }
+
+    pub fn with_capacity(bytes: usize) -> Self {
+        BitWriter {
+            bytes: Vec::with_capacity(bytes),
+            current: 0,
+            used: 0,
+            taken: 0,
+        }
+    }
+
+    pub fn write_bit(&mut self, bit: bool) {
+        self.current = (self.current << 1) | bit as u8;
+        self.used += 1;
+        if self.used == 8 {
+            self.bytes.push(self.current);
+            self.current = 0;
+            self.used = 0;
+        }
+    }
+
+    /// Writes the lowest `count` bits of `value`, the highest of them first.
+    pub fn write_bits(&mut self, value: u64, count: u32) {
+        assert!(
+            count <= 64,
+            "Can't write more than 64 bits at once: {}",
+            count
+        );
+        for shift in (0..count).rev() {
+            self.write_bit((value >> shift) & 1 == 1);
+        }
+    }
+
+    /// Number of bits written so far.
+    pub fn bit_len(&self) -> usize {
+        (self.taken + self.bytes.len()) * 8 + self.used as usize
+    }
+
+    /// Removes and returns the bytes which are already complete, so that they can be written
+    /// out while more bits are coming.
+    pub fn take_bytes(&mut self) -> Vec<u8> {
+        self.taken += self.bytes.len();
+        std::mem::take(&mut self.bytes)
+    }
+
+    /// Returns the written bytes, the last one padded with zero bits.
+    pub fn finish(mut self) -> Vec<u8> {
+        if self.used > 0 {
+            self.bytes.push(self.current << (8 - self.used));
+        }
+        self.bytes
+    }
+}
+
+impl Default for BitWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reads bits written by `BitWriter`.
+pub struct BitReader<'a> {
+    bytes: &'a [u8],
+    // index of the next bit to read
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        BitReader { bytes, position: 0 }
+    }
+
+    pub fn read_bit(&mut self) -> Option<bool> {
+        let byte = self.bytes.get(self.position / 8)?;
+        let bit = (byte >> (7 - self.position % 8)) & 1 == 1;
+        self.position += 1;
+        Some(bit)
+    }
+
+    /// Reads `count` bits into the lowest bits of the result, the first read bit being the
+    /// highest of them.
+    pub fn read_bits(&mut self, count: u32) -> Option<u64> {
+        assert!(
+            count <= 64,
+            "Can't read more than 64 bits at once: {}",
+            count
+        );
+        if self.remaining() < count as usize {
+            return None;
+        }
+        let mut value = 0u64;
+        for _ in 0..count {
+            value = (value << 1) | self.read_bit()? as u64;
+        }
+        Some(value)
+    }
+
+    /// Returns the next `count` bits like `read_bits` without consuming them, bits after the end
+    /// of the input read as zeros.
+    pub fn peek_bits(&self, count: u32) -> u64 {
+        assert!(
+            count <= 57,
+            "Can't peek more than 57 bits at once: {}",
+            count
+        );
+        let first = self.position / 8;
+        let offset = self.position % 8;
+        if let Some(bytes) = self.bytes.get(first..first + 8) {
+            let value = u64::from_be_bytes(bytes.try_into().unwrap()) << offset;
+            return value.checked_shr(64 - count).unwrap_or(0);
+        }
+        let needed = (offset + count as usize).div_ceil(8);
+        let mut value = 0u64;
+        for i in 0..needed {
+            value = (value << 8) | *self.bytes.get(first + i).unwrap_or(&0) as u64;
+        }
+        (value >> (needed * 8 - offset - count as usize)) & ((1 << count) - 1)
+    }
+
+    /// Consumes `count` bits, or none if fewer are left.
+    pub fn skip_bits(&mut self, count: u32) -> Option<()> {
+        if self.remaining() < count as usize {
+            return None;
+        }
+        self.position += count as usize;
+        Some(())
+    }
+
+    /// Number of bits left, including the padding of the last byte.
+    pub fn remaining(&self) -> usize {
+        self.bytes.len() * 8 - self.position
+    }
+
+    /// Number of bits read so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bit_writer_reader() {
+        let mut writer = BitWriter::new();
+        writer.write_bit(true);
+        writer.write_bits(0b0110, 4);
+        writer.write_bits(u64::MAX, 64);
+        writer.write_bits(0, 0);
+        writer.write_bits(0b101, 3);
+        assert_eq!(writer.bit_len(), 72);
+        writer.write_bit(true);
+        let bytes = writer.finish();
+        assert_eq!(bytes.len(), 10);
+        assert_eq!(bytes[0], 0b1011_0111);
+        assert_eq!(bytes[9], 0b1000_0000);
+
+        let mut reader = BitReader::new(&bytes);
+        assert_eq!(reader.read_bit(), Some(true));
+        assert_eq!(reader.read_bits(4), Some(0b0110));
+        assert_eq!(reader.read_bits(64), Some(u64::MAX));
+        assert_eq!(reader.read_bits(3), Some(0b101));
+        assert_eq!(reader.position(), 72);
+        assert_eq!(reader.peek_bits(12), 0b1000_0000_0000);
+        assert_eq!(reader.read_bits(8), Some(0b1000_0000));
+        assert_eq!(reader.read_bit(), None);
+        assert_eq!(reader.read_bits(1), None);
+
+        let mut reader = BitReader::new(&bytes);
+        assert_eq!(reader.peek_bits(0), 0);
+        assert_eq!(reader.skip_bits(3), Some(()));
+        assert_eq!(reader.peek_bits(6), 0b10_1111);
+        assert_eq!(reader.peek_bits(57) >> 49, 0b1011_1111);
+        assert_eq!(reader.position(), 3);
+        assert_eq!(reader.skip_bits(78), None);
+        assert_eq!(reader.skip_bits(77), Some(()));
+        assert_eq!(reader.remaining(), 0);
+
+        assert!(BitWriter::new().finish().is_empty());
+
+        let mut writer = BitWriter::new();
+        writer.write_bits(0xABC, 12);
+        assert_eq!(writer.take_bytes(), vec![0xAB]);
+        assert!(writer.take_bytes().is_empty());
+        writer.write_bits(0xD, 4);
+        assert_eq!(writer.bit_len(), 16);
+        assert_eq!(writer.finish(), vec![0xCD]);
+    }
+}
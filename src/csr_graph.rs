@@ -0,0 +1,295 @@
+use crate::graph::Graph;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Directed,
+    /// Every edge is stored in both directions, but counted once.
+    Undirected,
+}
+
+/// Graph in compressed sparse row form, generic over the vertex payloads `V` and the edge
+/// weights `E`, `()` for none.
+///
+/// Vertices are numbered `0..vertex_count()` in the order the builder first saw them. The
+/// edges leaving vertex `v` are `targets[offsets[v]..offsets[v + 1]]`, with their weights at
+/// the same positions, so an edge takes 4 bytes plus its weight and a vertex 8 bytes plus its
+/// payload, on top of the index from payloads to vertices.
+#[derive(Debug, Clone)]
+pub struct CsrGraph<V, E> {
+    direction: Direction,
+    payloads: Vec<V>,
+    index: HashMap<V, u32>,
+    offsets: Vec<usize>,
+    targets: Vec<u32>,
+    weights: Vec<E>,
+    edge_count: usize,
+}
+
+impl<V: Clone + Eq + Hash, E: Clone> CsrGraph<V, E> {
+    pub fn builder(direction: Direction) -> GraphBuilder<V, E> {
+        GraphBuilder::new(direction)
+    }
+
+    /// Graph of the edges, its vertices being their endpoints.
+    pub fn from_edges<I: IntoIterator<Item = (V, V, E)>>(direction: Direction, edges: I) -> Self {
+        let mut builder = GraphBuilder::new(direction);
+        for (from, to, weight) in edges {
+            builder.add_edge(from, to, weight);
+        }
+        builder.build()
+    }
+
+    /// The graph of the reversed edges, the graph itself if undirected.
+    pub fn transpose(&self) -> Self {
+        if self.direction == Direction::Undirected {
+            return self.clone();
+        }
+        let mut builder = GraphBuilder::with_capacity(self.direction, 0, self.edge_count);
+        builder.payloads = self.payloads.clone();
+        builder.index = self.index.clone();
+        for v in 0..self.vertex_count() {
+            for (u, weight) in self.neighbors(v) {
+                builder.edges.push((u as u32, v as u32, weight.clone()));
+            }
+        }
+        builder.build()
+    }
+}
+
+impl<V, E> CsrGraph<V, E> {
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.payloads.len()
+    }
+
+    /// Number of edges added, an undirected edge counting once.
+    pub fn edge_count(&self) -> usize {
+        self.edge_count
+    }
+
+    pub fn payload(&self, v: usize) -> &V {
+        &self.payloads[v]
+    }
+
+    /// Payloads of all the vertices, indexed by vertex.
+    pub fn payloads(&self) -> &[V] {
+        &self.payloads
+    }
+
+    /// Number of edges leaving `v`.
+    pub fn degree(&self, v: usize) -> usize {
+        self.offsets[v + 1] - self.offsets[v]
+    }
+
+    /// Heads and weights of the edges leaving `v`, in the order they were added.
+    pub fn neighbors(&self, v: usize) -> impl Iterator<Item = (usize, &E)> + '_ {
+        let range = self.offsets[v]..self.offsets[v + 1];
+        self.targets[range.clone()]
+            .iter()
+            .map(|u| *u as usize)
+            .zip(&self.weights[range])
+    }
+}
+
+impl<V: Eq + Hash, E> CsrGraph<V, E> {
+    /// Vertex of the payload, if any edge or `add_vertex` brought it in.
+    pub fn vertex(&self, payload: &V) -> Option<usize> {
+        self.index.get(payload).map(|v| *v as usize)
+    }
+}
+
+impl From<&Graph> for CsrGraph<usize, ()> {
+    /// Directed graph of the edges of `graph`, keeping its vertices without edges.
+    fn from(graph: &Graph) -> Self {
+        let mut vertices: Vec<usize> = graph.vertices().iter().copied().collect();
+        vertices.sort_unstable();
+        let mut builder = GraphBuilder::new(Direction::Directed);
+        for v in vertices {
+            builder.add_vertex(v);
+        }
+        for (from, to) in graph.edges() {
+            builder.add_edge(*from, *to, ());
+        }
+        builder.build()
+    }
+}
+
+/// Collects the vertices and edges of a `CsrGraph`. Vertices come from the endpoints of the
+/// edges, `add_vertex` is only needed for vertices without any.
+pub struct GraphBuilder<V, E> {
+    direction: Direction,
+    payloads: Vec<V>,
+    index: HashMap<V, u32>,
+    edges: Vec<(u32, u32, E)>,
+}
+
+impl<V: Clone + Eq + Hash, E> GraphBuilder<V, E> {
+    pub fn new(direction: Direction) -> Self {
+        GraphBuilder {
+            direction,
+            payloads: Vec::new(),
+            index: HashMap::new(),
+            edges: Vec::new(),
+        }
+    }
+
+    /// Builder expecting about `vertices` vertices and `edges` edges.
+    pub fn with_capacity(direction: Direction, vertices: usize, edges: usize) -> Self {
+        GraphBuilder {
+            direction,
+            payloads: Vec::with_capacity(vertices),
+            index: HashMap::with_capacity(vertices),
+            edges: Vec::with_capacity(edges),
+        }
+    }
+
+    /// Adds the vertex of the payload unless there is one already, returns the vertex.
+    pub fn add_vertex(&mut self, payload: V) -> usize {
+        if let Some(v) = self.index.get(&payload) {
+            return *v as usize;
+        }
+        let v = u32::try_from(self.payloads.len()).expect("More vertices than fit in u32");
+        self.payloads.push(payload.clone());
+        self.index.insert(payload, v);
+        v as usize
+    }
+
+    /// Adds an edge between the vertices of the payloads, adding the vertices first if needed.
+    pub fn add_edge(&mut self, from: V, to: V, weight: E) {
+        let from = self.add_vertex(from) as u32;
+        let to = self.add_vertex(to) as u32;
+        self.edges.push((from, to, weight));
+    }
+
+    pub fn build(self) -> CsrGraph<V, E>
+    where
+        E: Clone,
+    {
+        let n = self.payloads.len();
+        let edge_count = self.edges.len();
+        let undirected = self.direction == Direction::Undirected;
+
+        // counting sort of the edges by tail, keeping the order they were added in
+        let mut offsets = vec![0usize; n + 1];
+        for (from, to, _) in &self.edges {
+            offsets[*from as usize + 1] += 1;
+            if undirected && from != to {
+                offsets[*to as usize + 1] += 1;
+            }
+        }
+        for v in 0..n {
+            offsets[v + 1] += offsets[v];
+        }
+
+        let mut next = offsets.clone();
+        let mut targets = vec![0u32; offsets[n]];
+        let mut weights: Vec<Option<E>> = vec![None; offsets[n]];
+        for (from, to, weight) in self.edges {
+            if undirected && from != to {
+                let slot = &mut next[to as usize];
+                targets[*slot] = from;
+                weights[*slot] = Some(weight.clone());
+                *slot += 1;
+            }
+            let slot = &mut next[from as usize];
+            targets[*slot] = to;
+            weights[*slot] = Some(weight);
+            *slot += 1;
+        }
+
+        CsrGraph {
+            direction: self.direction,
+            payloads: self.payloads,
+            index: self.index,
+            offsets,
+            targets,
+            weights: weights.into_iter().map(Option::unwrap).collect(),
+            edge_count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn neighbors<V, E: Clone>(graph: &CsrGraph<V, E>, v: usize) -> Vec<(usize, E)> {
+        graph
+            .neighbors(v)
+            .map(|(u, weight)| (u, weight.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_directed() {
+        let graph = CsrGraph::from_edges(
+            Direction::Directed,
+            [("a", "b", 1), ("b", "c", 2), ("a", "c", 3), ("c", "a", 4)],
+        );
+        assert_eq!(graph.vertex_count(), 3);
+        assert_eq!(graph.edge_count(), 4);
+        assert_eq!(graph.payloads(), ["a", "b", "c"]);
+        let a = graph.vertex(&"a").unwrap();
+        let c = graph.vertex(&"c").unwrap();
+        assert_eq!(graph.vertex(&"d"), None);
+        assert_eq!(neighbors(&graph, a), [(1, 1), (2, 3)]);
+        assert_eq!(neighbors(&graph, c), [(0, 4)]);
+        assert_eq!(graph.degree(1), 1);
+
+        let transpose = graph.transpose();
+        assert_eq!(transpose.edge_count(), 4);
+        assert_eq!(neighbors(&transpose, c), [(0, 3), (1, 2)]);
+        assert_eq!(neighbors(&transpose, a), [(2, 4)]);
+    }
+
+    #[test]
+    fn test_undirected() {
+        let mut builder = GraphBuilder::new(Direction::Undirected);
+        builder.add_edge(10, 20, ());
+        builder.add_edge(20, 30, ());
+        builder.add_edge(30, 30, ());
+        assert_eq!(builder.add_vertex(40), 3);
+        let graph = builder.build();
+
+        assert_eq!(graph.vertex_count(), 4);
+        assert_eq!(graph.edge_count(), 3);
+        assert_eq!(neighbors(&graph, 0), [(1, ())]);
+        assert_eq!(neighbors(&graph, 1), [(0, ()), (2, ())]);
+        // a loop is stored once
+        assert_eq!(neighbors(&graph, 2), [(1, ()), (2, ())]);
+        assert_eq!(graph.degree(3), 0);
+    }
+
+    #[test]
+    fn test_from_graph() {
+        let edges = vec![(1, 2), (2, 3), (3, 1), (3, 4)];
+        let mut adjacency_list = HashMap::new();
+        for (from, to) in &edges {
+            adjacency_list
+                .entry(*from)
+                .or_insert_with(HashSet::new)
+                .insert(*to);
+        }
+        let graph = Graph::from(adjacency_list, (1..=5).collect(), edges);
+        let csr = CsrGraph::from(&graph);
+
+        assert_eq!(csr.vertex_count(), 5);
+        assert_eq!(csr.edge_count(), 4);
+        for v in 1..=5 {
+            let mut expected: Vec<usize> = graph.neighbors(v).collect();
+            let mut actual: Vec<usize> = csr
+                .neighbors(csr.vertex(&v).unwrap())
+                .map(|(u, _)| *csr.payload(u))
+                .collect();
+            expected.sort_unstable();
+            actual.sort_unstable();
+            assert_eq!(actual, expected);
+        }
+    }
+}
@@ -0,0 +1,334 @@
+use std::cmp;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditKind {
+    /// `old[old]` equals `new[new]` and is kept.
+    Equal,
+    /// `old[old]` is deleted.
+    Delete,
+    /// `new[new]` is inserted.
+    Insert,
+}
+
+/// Step of an edit script, at the positions `old` and `new` reached in both sequences before
+/// the step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edit {
+    pub kind: EditKind,
+    pub old: usize,
+    pub new: usize,
+}
+
+/// Shortest edit script turning `old` into `new` (Myers), its equal elements being a longest
+/// common subsequence.
+///
+/// The furthest reaching paths of every diagonal `k = x - y` are extended for `d = 0, 1, ...`
+/// edits from both ends at once, following runs of equal elements for free, until a forward
+/// and a backward path overlap. The snake where they meet is on a shortest path and splits
+/// the sequences in two parts of about half the edits, diffed the same way, which takes
+/// O((n + m) * d) time and O(n + m) space.
+pub fn myers_diff<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Edit> {
+    let offset = old.len() + new.len() + 1;
+    let mut differ = Differ {
+        old,
+        new,
+        offset: offset as isize,
+        forward: vec![0; 2 * offset + 1],
+        backward: vec![0; 2 * offset + 1],
+        script: Vec::with_capacity(cmp::max(old.len(), new.len())),
+    };
+    differ.diff(0, old.len(), 0, new.len());
+    differ.script
+}
+
+struct Differ<'a, T> {
+    old: &'a [T],
+    new: &'a [T],
+    offset: isize,
+    // furthest x reached on diagonal k from the start and from the end, at k + offset
+    forward: Vec<isize>,
+    backward: Vec<isize>,
+    script: Vec<Edit>,
+}
+
+impl<T: PartialEq> Differ<'_, T> {
+    // appends the script of old[x..x_end] into new[y..y_end]
+    fn diff(&mut self, mut x: usize, x_end: usize, mut y: usize, y_end: usize) {
+        while x < x_end && y < y_end && self.old[x] == self.new[y] {
+            self.script.push(edit(EditKind::Equal, x, y));
+            x += 1;
+            y += 1;
+        }
+        let (mut u, mut v) = (x_end, y_end);
+        while u > x && v > y && self.old[u - 1] == self.new[v - 1] {
+            u -= 1;
+            v -= 1;
+        }
+
+        if x == u {
+            self.script
+                .extend((y..v).map(|new| edit(EditKind::Insert, x, new)));
+        } else if y == v {
+            self.script
+                .extend((x..u).map(|old| edit(EditKind::Delete, old, y)));
+        } else {
+            let (snake_x, snake_y, snake_u, snake_v) = self.middle_snake(x, u, y, v);
+            self.diff(x, snake_x, y, snake_y);
+            self.script.extend(
+                (0..snake_u - snake_x).map(|i| edit(EditKind::Equal, snake_x + i, snake_y + i)),
+            );
+            self.diff(snake_u, u, snake_v, v);
+        }
+
+        self.script
+            .extend((0..x_end - u).map(|i| edit(EditKind::Equal, u + i, v + i)));
+    }
+
+    // start and end of the snake where the forward and backward paths of old[x..u] and
+    // new[y..v] first overlap, the end of both a shortest path and a path of half its edits
+    fn middle_snake(
+        &mut self,
+        x: usize,
+        u: usize,
+        y: usize,
+        v: usize,
+    ) -> (usize, usize, usize, usize) {
+        let (old, new) = (&self.old[x..u], &self.new[y..v]);
+        let (n, m) = (old.len() as isize, new.len() as isize);
+        // the diagonal of the end, where the backward diagonal 0 is
+        let delta = n - m;
+        let offset = self.offset;
+        let at = |k: isize| (k + offset) as usize;
+        self.forward[at(1)] = 0;
+        self.backward[at(1)] = 0;
+
+        for d in 0..=(n + m + 1) / 2 {
+            for k in (-d..=d).step_by(2) {
+                let forward = &mut self.forward;
+                let start = if k == -d || (k != d && forward[at(k - 1)] < forward[at(k + 1)]) {
+                    forward[at(k + 1)]
+                } else {
+                    forward[at(k - 1)] + 1
+                };
+                let mut end = start;
+                while end < n && end - k < m && old[end as usize] == new[(end - k) as usize] {
+                    end += 1;
+                }
+                forward[at(k)] = end;
+                // the backward paths of d - 1 edits, on diagonals of the other parity
+                let c = delta - k;
+                if delta % 2 != 0 && (1 - d..d).contains(&c) && end + self.backward[at(c)] >= n {
+                    return (
+                        x + start as usize,
+                        y + (start - k) as usize,
+                        x + end as usize,
+                        y + (end - k) as usize,
+                    );
+                }
+            }
+
+            // backward paths, x counted from the end of both sequences
+            for c in (-d..=d).step_by(2) {
+                let backward = &mut self.backward;
+                let start = if c == -d || (c != d && backward[at(c - 1)] < backward[at(c + 1)]) {
+                    backward[at(c + 1)]
+                } else {
+                    backward[at(c - 1)] + 1
+                };
+                let mut end = start;
+                while end < n
+                    && end - c < m
+                    && old[(n - end - 1) as usize] == new[(m - end + c - 1) as usize]
+                {
+                    end += 1;
+                }
+                backward[at(c)] = end;
+                let k = delta - c;
+                if delta % 2 == 0 && (-d..=d).contains(&k) && self.forward[at(k)] + end >= n {
+                    return (
+                        x + (n - end) as usize,
+                        y + (m - end + c) as usize,
+                        x + (n - start) as usize,
+                        y + (m - start + c) as usize,
+                    );
+                }
+            }
+        }
+        unreachable!("the paths overlap by half the edit distance")
+    }
+}
+
+fn edit(kind: EditKind, old: usize, new: usize) -> Edit {
+    Edit { kind, old, new }
+}
+
+/// Line based diff of two texts in the unified format of `diff -u`, with `context` unchanged
+/// lines around the changes, or an empty string for equal texts.
+///
+/// A last line without a line break is marked by "\ No newline at end of file", as it differs
+/// from the same line with one.
+pub fn unified_diff(
+    old: &str,
+    new: &str,
+    old_name: &str,
+    new_name: &str,
+    context: usize,
+) -> String {
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let script = myers_diff(&old_lines, &new_lines);
+    let changes: Vec<usize> = (0..script.len())
+        .filter(|i| script[*i].kind != EditKind::Equal)
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    let mut output = format!("--- {}\n+++ {}\n", old_name, new_name);
+    let mut first = 0;
+    while first < changes.len() {
+        // changes apart by at most twice the context share a hunk
+        let mut last = first;
+        while last + 1 < changes.len() && changes[last + 1] - changes[last] <= 2 * context + 1 {
+            last += 1;
+        }
+        let start = changes[first].saturating_sub(context);
+        let end = cmp::min(changes[last] + context + 1, script.len());
+        let hunk = &script[start..end];
+
+        let old_len = hunk.iter().filter(|e| e.kind != EditKind::Insert).count();
+        let new_len = hunk.iter().filter(|e| e.kind != EditKind::Delete).count();
+        writeln!(
+            output,
+            "@@ -{} +{} @@",
+            hunk_range(hunk[0].old, old_len),
+            hunk_range(hunk[0].new, new_len)
+        )
+        .unwrap();
+        for edit in hunk {
+            let (prefix, line) = match edit.kind {
+                EditKind::Equal => (' ', old_lines[edit.old]),
+                EditKind::Delete => ('-', old_lines[edit.old]),
+                EditKind::Insert => ('+', new_lines[edit.new]),
+            };
+            output.push(prefix);
+            output.push_str(line);
+            if !line.ends_with('\n') {
+                output.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+        first = last + 1;
+    }
+    output
+}
+
+// the 1-based first line and the number of lines, an empty range starting at the line before
+fn hunk_range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, len),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edit_distance;
+    use rand::{thread_rng, Rng};
+
+    // applies the script to old, checking that it is consistent
+    fn apply<T: Clone + PartialEq + std::fmt::Debug>(
+        script: &[Edit],
+        old: &[T],
+        new: &[T],
+    ) -> Vec<T> {
+        let (mut x, mut y) = (0, 0);
+        let mut result = Vec::new();
+        for edit in script {
+            assert_eq!((edit.old, edit.new), (x, y));
+            match edit.kind {
+                EditKind::Equal => {
+                    assert_eq!(old[x], new[y]);
+                    result.push(old[x].clone());
+                    x += 1;
+                    y += 1;
+                }
+                EditKind::Delete => x += 1,
+                EditKind::Insert => {
+                    result.push(new[y].clone());
+                    y += 1;
+                }
+            }
+        }
+        assert_eq!((x, y), (old.len(), new.len()));
+        result
+    }
+
+    #[test]
+    fn test_myers_diff() {
+        let script = myers_diff(b"ABCABBA", b"CBABAC");
+        assert_eq!(apply(&script, b"ABCABBA", b"CBABAC"), b"CBABAC");
+        // the edit distance of the paper
+        let edits = script.iter().filter(|e| e.kind != EditKind::Equal).count();
+        assert_eq!(edits, 5);
+
+        assert!(myers_diff::<u8>(b"", b"").is_empty());
+        assert_eq!(
+            myers_diff(b"", b"ab")
+                .iter()
+                .map(|e| e.kind)
+                .collect::<Vec<_>>(),
+            [EditKind::Insert, EditKind::Insert]
+        );
+
+        // nothing in common, the worst case of the edit distance
+        let old: Vec<u32> = (0..2000).collect();
+        let new: Vec<u32> = (2000..4000).collect();
+        let script = myers_diff(&old, &new);
+        assert_eq!(script.len(), 4000);
+        assert_eq!(apply(&script, &old, &new), new);
+
+        let mut rng = thread_rng();
+        for _ in 0..200 {
+            let old: Vec<u8> = (0..rng.gen_range(0..30))
+                .map(|_| rng.gen_range(0..3))
+                .collect();
+            let new: Vec<u8> = (0..rng.gen_range(0..30))
+                .map(|_| rng.gen_range(0..3))
+                .collect();
+            let script = myers_diff(&old, &new);
+            assert_eq!(apply(&script, &old, &new), new);
+            let equal = script.iter().filter(|e| e.kind == EditKind::Equal).count();
+            assert_eq!(
+                equal,
+                edit_distance::longest_common_subsequence(&old, &new).len()
+            );
+        }
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl";
+        assert_eq!(unified_diff(old, old, "old", "new", 3), "");
+        assert_eq!(
+            unified_diff(old, new, "old", "new", 1),
+            "--- old\n+++ new\n\
+             @@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n\
+             @@ -11 +11,2 @@\n k\n+l\n\\ No newline at end of file\n"
+        );
+        // both changes share a hunk with more context
+        assert_eq!(
+            unified_diff(old, new, "old", "new", 5)
+                .matches("@@ -")
+                .count(),
+            1
+        );
+        assert_eq!(
+            unified_diff("", "x\n", "old", "new", 3),
+            "--- old\n+++ new\n@@ -0,0 +1 @@\n+x\n"
+        );
+    }
+}
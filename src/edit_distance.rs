@@ -0,0 +1,106 @@
+use std::cmp;
+
+/// Levenshtein distance: the least number of insertions, deletions and substitutions turning
+/// `a` into `b`, in O(n * m) time and O(m) space.
+pub fn levenshtein<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, x) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, y) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(x != y);
+            current[j + 1] = cmp::min(substitution, cmp::min(previous[j + 1], current[j]) + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Damerau–Levenshtein distance, counting the transposition of two adjacent elements as a
+/// single edit too.
+///
+/// This is the optimal string alignment variant: no element is edited again after being
+/// transposed, so "ca" is 3 edits away from "abc" rather than 2.
+pub fn damerau_levenshtein<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    // rows of the prefixes of a one and two elements shorter
+    let mut before_previous = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for i in 0..a.len() {
+        current[0] = i + 1;
+        for j in 0..b.len() {
+            let substitution = previous[j] + usize::from(a[i] != b[j]);
+            let mut distance = cmp::min(substitution, cmp::min(previous[j + 1], current[j]) + 1);
+            if i > 0 && j > 0 && a[i] == b[j - 1] && a[i - 1] == b[j] {
+                distance = cmp::min(distance, before_previous[j - 1] + 1);
+            }
+            current[j + 1] = distance;
+        }
+        std::mem::swap(&mut before_previous, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// A longest common subsequence of `a` and `b`, reconstructed from the O(n * m) table of the
+/// LCS lengths of all the suffixes.
+pub fn longest_common_subsequence<T: Clone + PartialEq>(a: &[T], b: &[T]) -> Vec<T> {
+    // lengths of the suffixes a[i..] and b[j..] at i * (m + 1) + j
+    let width = b.len() + 1;
+    let mut lengths = vec![0u32; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i * width + j] = if a[i] == b[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                cmp::max(lengths[(i + 1) * width + j], lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut subsequence = Vec::with_capacity(lengths[0] as usize);
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            subsequence.push(a[i].clone());
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    subsequence
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein(b"kitten", b"sitting"), 3);
+        assert_eq!(levenshtein(b"flaw", b"lawn"), 2);
+        assert_eq!(levenshtein(b"", b"abc"), 3);
+        assert_eq!(levenshtein(b"abc", b"abc"), 0);
+        assert_eq!(levenshtein(b"ab", b"ba"), 2);
+
+        assert_eq!(damerau_levenshtein(b"ab", b"ba"), 1);
+        assert_eq!(damerau_levenshtein(b"ca", b"abc"), 3);
+        assert_eq!(damerau_levenshtein(b"kitten", b"sitting"), 3);
+        assert_eq!(damerau_levenshtein(b"abcdef", b"badcfe"), 3);
+        assert_eq!(damerau_levenshtein(b"", b""), 0);
+    }
+
+    #[test]
+    fn test_longest_common_subsequence() {
+        assert_eq!(longest_common_subsequence(b"ABCBDAB", b"BDCABA").len(), 4);
+        assert_eq!(longest_common_subsequence(b"AGGTAB", b"GXTXAYB"), b"GTAB");
+        assert_eq!(longest_common_subsequence(b"abc", b"def"), b"");
+
+        let a: Vec<&str> = "the quick brown fox".split(' ').collect();
+        let b: Vec<&str> = "the lazy brown dog fox".split(' ').collect();
+        assert_eq!(longest_common_subsequence(&a, &b), ["the", "brown", "fox"]);
+    }
+}
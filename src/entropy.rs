@@ -0,0 +1,291 @@
+use crate::huffman::{self, DecodeError};
+use std::time::{Duration, Instant};
+
+/// Frequencies of a `FrequencyModel` sum to `1 << SCALE_BITS`.
+pub const SCALE_BITS: u32 = 14;
+pub const TOTAL: u32 = 1 << SCALE_BITS;
+
+/// Coder of byte sequences driven by a static `FrequencyModel`.
+///
+/// The model isn't part of the output, both sides have to agree on it and on the number of
+/// encoded bytes, so coders are compared on their payload alone.
+pub trait EntropyCoder {
+    fn name(&self) -> &'static str;
+
+    /// Encodes `data`, every byte of which must have a positive frequency in `model`.
+    fn encode(&self, model: &FrequencyModel, data: &[u8]) -> Vec<u8>;
+
+    /// Decodes `len` bytes encoded by `encode` with the same model.
+    fn decode(
+        &self,
+        model: &FrequencyModel,
+        encoded: &[u8],
+        len: usize,
+    ) -> Result<Vec<u8>, DecodeError>;
+}
+
+/// Probabilities of the 256 byte values as integer frequencies summing to `TOTAL`.
+///
+/// Every byte value with a positive count keeps a frequency of at least 1, so it stays
+/// encodable after the scaling.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrequencyModel {
+    frequencies: Vec<u32>,
+    // cumulative[s] is the sum of the frequencies of the symbols below s
+    cumulative: Vec<u32>,
+    // symbol owning every slot of 0..TOTAL
+    symbols: Vec<u8>,
+}
+
+impl FrequencyModel {
+    /// Scales the counts of the byte values, indexed by value, to sum to `TOTAL`. Counts of all
+    /// zeros give a model of no symbols, which only encodes empty data.
+    pub fn new(counts: &[usize]) -> Self {
+        assert!(
+            counts.len() <= 256,
+            "Expected counts of at most 256 byte values, got {}",
+            counts.len()
+        );
+        let mut frequencies = vec![0u32; 256];
+        if counts.iter().any(|count| *count > 0) {
+            let sum: u128 = counts.iter().map(|count| *count as u128).sum();
+            for (symbol, count) in counts.iter().enumerate() {
+                if *count > 0 {
+                    let scaled = *count as u128 * TOTAL as u128 / sum;
+                    frequencies[symbol] = std::cmp::max(scaled as u32, 1);
+                }
+            }
+
+            // rounding down loses at most a slot per symbol, raising the rare ones to 1 adds
+            // at most a slot per symbol
+            let mut scaled_sum: u32 = frequencies.iter().sum();
+            while scaled_sum > TOTAL {
+                let largest = (0..256).max_by_key(|s| frequencies[*s]).unwrap();
+                frequencies[largest] -= 1;
+                scaled_sum -= 1;
+            }
+            let largest = (0..256).max_by_key(|s| frequencies[*s]).unwrap();
+            frequencies[largest] += TOTAL - scaled_sum;
+        }
+
+        let mut cumulative = vec![0u32; 257];
+        for symbol in 0..256 {
+            cumulative[symbol + 1] = cumulative[symbol] + frequencies[symbol];
+        }
+        let mut symbols = Vec::with_capacity(TOTAL as usize);
+        for (symbol, frequency) in frequencies.iter().enumerate() {
+            symbols.extend(std::iter::repeat_n(symbol as u8, *frequency as usize));
+        }
+
+        FrequencyModel {
+            frequencies,
+            cumulative,
+            symbols,
+        }
+    }
+
+    /// Model of the byte frequencies of `data`.
+    pub fn from_data(data: &[u8]) -> Self {
+        Self::new(&huffman::byte_frequencies(data))
+    }
+
+    pub fn frequency(&self, symbol: u8) -> u32 {
+        self.frequencies[symbol as usize]
+    }
+
+    /// Sum of the frequencies of the symbols below `symbol`.
+    pub fn cumulative(&self, symbol: u8) -> u32 {
+        self.cumulative[symbol as usize]
+    }
+
+    /// Symbol whose range `cumulative..cumulative + frequency` contains `slot`.
+    pub fn symbol(&self, slot: u32) -> u8 {
+        self.symbols[slot as usize]
+    }
+
+    /// The scaled frequencies of all the byte values, indexed by value.
+    pub fn frequencies(&self) -> &[u32] {
+        &self.frequencies
+    }
+
+    /// Shannon entropy of the model in bits per symbol, the lower bound of the output of any
+    /// coder using it.
+    pub fn entropy(&self) -> f64 {
+        self.frequencies
+            .iter()
+            .filter(|frequency| **frequency > 0)
+            .map(|frequency| {
+                let p = *frequency as f64 / TOTAL as f64;
+                -p * p.log2()
+            })
+            .sum()
+    }
+}
+
+/// Outcome of a coder on an input.
+#[derive(Debug, Clone)]
+pub struct CoderReport {
+    pub name: &'static str,
+    pub encoded_len: usize,
+    pub bits_per_symbol: f64,
+    pub encode_time: Duration,
+    pub decode_time: Duration,
+}
+
+/// Runs every coder on `data` with the model of its byte frequencies, checking that the
+/// decoded data matches.
+///
+/// Panics if a coder doesn't restore `data`.
+pub fn compare(coders: &[&dyn EntropyCoder], data: &[u8]) -> Vec<CoderReport> {
+    let model = FrequencyModel::from_data(data);
+    coders
+        .iter()
+        .map(|coder| {
+            let start = Instant::now();
+            let encoded = coder.encode(&model, data);
+            let encode_time = start.elapsed();
+
+            let start = Instant::now();
+            let decoded = coder.decode(&model, &encoded, data.len());
+            let decode_time = start.elapsed();
+            assert!(
+                decoded.as_deref() == Ok(data),
+                "{} didn't restore the data",
+                coder.name()
+            );
+
+            CoderReport {
+                name: coder.name(),
+                encoded_len: encoded.len(),
+                bits_per_symbol: encoded.len() as f64 * 8.0 / std::cmp::max(data.len(), 1) as f64,
+                encode_time,
+                decode_time,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arithmetic_coding::ArithmeticCoder;
+    use crate::huffman::{HuffmanCoder, MAX_DECODED_LEN};
+    use crate::rans::RansCoder;
+    use rand::{thread_rng, Rng};
+
+    #[test]
+    fn test_frequency_model() {
+        let model = FrequencyModel::new(&[1, 0, 3]);
+        assert_eq!(model.frequency(0), TOTAL / 4);
+        assert_eq!(model.frequency(1), 0);
+        assert_eq!(model.frequency(2), TOTAL * 3 / 4);
+        assert_eq!(model.cumulative(2), TOTAL / 4);
+        assert_eq!(model.symbol(0), 0);
+        assert_eq!(model.symbol(TOTAL / 4 - 1), 0);
+        assert_eq!(model.symbol(TOTAL / 4), 2);
+        assert_eq!(model.symbol(TOTAL - 1), 2);
+
+        // rare symbols keep a slot, and the frequencies still sum to the total
+        let mut counts = vec![1; 256];
+        counts[7] = 1_000_000_000;
+        let model = FrequencyModel::new(&counts);
+        assert!(model.frequencies().iter().all(|frequency| *frequency >= 1));
+        assert_eq!(model.frequencies().iter().sum::<u32>(), TOTAL);
+        assert_eq!(model.frequency(7), TOTAL - 255);
+
+        assert_eq!(
+            FrequencyModel::new(&[0; 256])
+                .frequencies()
+                .iter()
+                .sum::<u32>(),
+            0
+        );
+        assert_eq!(FrequencyModel::from_data(b"abab").entropy(), 1.0);
+    }
+
+    #[test]
+    fn test_coders_round_trip() {
+        let coders: [&dyn EntropyCoder; 3] = [&HuffmanCoder, &ArithmeticCoder, &RansCoder];
+        let mut rng = thread_rng();
+        let skewed: Vec<u8> = (0..50_000)
+            .map(|_| rng.gen_range(0..64) / rng.gen_range(1..16))
+            .collect();
+        let uniform: Vec<u8> = (0..50_000).map(|_| rng.gen()).collect();
+        for data in [
+            &b""[..],
+            b"a",
+            b"aaaaaaaaaaaaaaaa",
+            b"abracadabra",
+            &skewed,
+            &uniform,
+        ] {
+            let model = FrequencyModel::from_data(data);
+            for coder in coders {
+                let encoded = coder.encode(&model, data);
+                assert_eq!(
+                    coder.decode(&model, &encoded, data.len()).unwrap(),
+                    data,
+                    "{}",
+                    coder.name()
+                );
+            }
+        }
+
+        // arithmetic coding and rANS get within a fraction of a bit of the ideal code length
+        // under the scaled model, Huffman loses more on skewed data
+        let reports = compare(&coders, &skewed);
+        let model = FrequencyModel::from_data(&skewed);
+        let ideal_bits: f64 = skewed
+            .iter()
+            .map(|byte| -(model.frequency(*byte) as f64 / TOTAL as f64).log2())
+            .sum();
+        let ideal = ideal_bits / skewed.len() as f64;
+        assert_eq!(reports[0].name, "huffman");
+        for report in &reports[1..] {
+            assert!(report.bits_per_symbol < ideal + 0.01, "{:?}", report);
+            assert!(report.bits_per_symbol < reports[0].bits_per_symbol);
+        }
+        assert!(reports[0].bits_per_symbol < ideal + 1.0);
+    }
+
+    #[test]
+    fn test_decode_truncated() {
+        let coders: [&dyn EntropyCoder; 3] = [&HuffmanCoder, &ArithmeticCoder, &RansCoder];
+        let data = b"this is an example of a huffman tree".repeat(20);
+        let model = FrequencyModel::from_data(&data);
+        for coder in coders {
+            let encoded = coder.encode(&model, &data);
+            assert_eq!(
+                coder.decode(&model, &encoded[..encoded.len() / 2], data.len()),
+                Err(DecodeError::UnexpectedEnd),
+                "{}",
+                coder.name()
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_too_long() {
+        let coders: [&dyn EntropyCoder; 2] = [&ArithmeticCoder, &RansCoder];
+        for data in [&b"aaaa"[..], b"abab"] {
+            let model = FrequencyModel::from_data(data);
+            for coder in coders {
+                let encoded = coder.encode(&model, data);
+                for input in [&[][..], &encoded] {
+                    assert_eq!(
+                        coder.decode(&model, input, usize::MAX),
+                        Err(DecodeError::TooLong),
+                        "{}",
+                        coder.name()
+                    );
+                    assert_eq!(
+                        coder.decode(&model, input, MAX_DECODED_LEN + 1),
+                        Err(DecodeError::TooLong),
+                        "{}",
+                        coder.name()
+                    );
+                }
+            }
+        }
+    }
+}
@@ -28,6 +28,19 @@ impl Graph {
         }
     }
 
+    pub fn vertices(&self) -> &HashSet<usize> {
+        &self.vertices
+    }
+
+    pub fn edges(&self) -> &[(usize, usize)] {
+        &self.edges
+    }
+
+    /// Heads of the edges leaving `v`.
+    pub fn neighbors(&self, v: usize) -> impl Iterator<Item = usize> + '_ {
+        self.adjacency_list.get(&v).into_iter().flatten().copied()
+    }
+
     pub fn depth_first_search(&self, s: usize) {
         // mark all vertices as unexplored
         let mut explored: HashSet<usize> = HashSet::with_capacity(self.vertices.len());
@@ -1,21 +1,12 @@
-use std::cmp;
-use std::cmp::PartialOrd;
+use std::cmp::{Ordering, PartialOrd};
 use std::fmt::Display;
 
-pub struct MinHeap<T: Display + PartialOrd + Copy + Ord> {
+pub struct MinHeap<T: Ord> {
     elements: Vec<T>,
 }
 
-impl<T: Display + PartialOrd + Copy + Ord> MinHeap<T> {
-    pub fn new() -> Self {
-        println!("Creating a new empty MinHeap");
-        MinHeap {
-            elements: Vec::<T>::new(),
-        }
-    }
-
+impl<T: Ord + Copy> MinHeap<T> {
     pub fn from(elements: &[T]) -> Self {
-        println!("Creating a MinHeap from elements.len()={}", elements.len());
         let mut heap = MinHeap {
             elements: Vec::<T>::with_capacity(elements.len()),
         };
@@ -25,6 +16,18 @@ impl<T: Display + PartialOrd + Copy + Ord> MinHeap<T> {
         heap
     }
 
+    pub fn get_min(&self) -> Option<T> {
+        self.peek_min().copied()
+    }
+}
+
+impl<T: Ord> MinHeap<T> {
+    pub fn new() -> Self {
+        MinHeap {
+            elements: Vec::<T>::new(),
+        }
+    }
+
     pub fn insert(&mut self, element: T) {
         self.elements.push(element);
 
@@ -97,37 +100,31 @@ impl<T: Display + PartialOrd + Copy + Ord> MinHeap<T> {
         return Some(min_element);
     }
 
-    pub fn get_min(&self) -> Option<T> {
-        if !self.elements.is_empty() {
-            return Some(self.elements[0]);
-            // let mut max = self.elements[0];
-            // for item in self.elements.iter() {
-            //     max = cmp::max(max, *item);
-            // }
-            // return Some(max);
-        }
-        None
+    pub fn peek_min(&self) -> Option<&T> {
+        self.elements.first()
     }
 
     pub fn len(&self) -> usize {
         self.elements.len()
     }
-}
 
-struct MaxHeap<T: Display + PartialOrd + Ord> {
-    elements: Vec<T>,
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
 }
 
-impl<T: Display + PartialOrd + Copy + Ord> MaxHeap<T> {
-    pub fn new() -> Self {
-        println!("Creating a new empty MaxHeap");
-        MaxHeap {
-            elements: Vec::<T>::new(),
-        }
+impl<T: Ord> Default for MinHeap<T> {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
+pub struct MaxHeap<T: Ord> {
+    elements: Vec<T>,
+}
+
+impl<T: Ord + Copy> MaxHeap<T> {
     pub fn from(elements: &[T]) -> Self {
-        println!("Creating a MaxHeap from elements.len()={}", elements.len());
         let mut heap = MaxHeap {
             elements: Vec::<T>::with_capacity(elements.len()),
         };
@@ -136,6 +133,14 @@ impl<T: Display + PartialOrd + Copy + Ord> MaxHeap<T> {
         }
         heap
     }
+}
+
+impl<T: Ord> MaxHeap<T> {
+    pub fn new() -> Self {
+        MaxHeap {
+            elements: Vec::<T>::new(),
+        }
+    }
 
     pub fn insert(&mut self, element: T) {
         self.elements.push(element);
@@ -209,21 +214,284 @@ impl<T: Display + PartialOrd + Copy + Ord> MaxHeap<T> {
         return Some(max_element);
     }
 
-    pub fn get_max(&self) -> Option<T> {
-        if !self.elements.is_empty() {
-            return Some(self.elements[0]);
-            // let mut min = self.elements[0];
-            // for item in self.elements.iter() {
-            //     min = cmp::min(min, *item);
-            // }
-            // return Some(min);
+    pub fn peek_max(&self) -> Option<&T> {
+        self.elements.first()
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+}
+
+impl<T: Ord> Default for MaxHeap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Double-ended priority queue (min-max heap by Atkinson et al.).
+///
+/// Elements are stored in a complete binary tree like in `MinHeap`, but the levels alternate:
+/// every element on an even level (the root is on level 0) is the minimum of its subtree and
+/// every element on an odd level is the maximum of its subtree. The minimum is at the root and
+/// the maximum is one of its children, so both can be peeked in O(1) and popped in O(log n).
+pub struct MinMaxHeap<T: Ord> {
+    elements: Vec<T>,
+}
+
+impl<T: Ord + Copy> MinMaxHeap<T> {
+    pub fn from(elements: &[T]) -> Self {
+        let mut heap = MinMaxHeap {
+            elements: Vec::<T>::with_capacity(elements.len()),
+        };
+        for item in elements.iter() {
+            heap.insert(*item);
         }
-        None
+        heap
+    }
+}
+
+impl<T: Ord> MinMaxHeap<T> {
+    pub fn new() -> Self {
+        MinMaxHeap {
+            elements: Vec::<T>::new(),
+        }
+    }
+
+    pub fn insert(&mut self, element: T) {
+        self.elements.push(element);
+
+        let index = self.elements.len() - 1;
+        if index == 0 {
+            return;
+        }
+
+        // the inserted element is either in the right place for its own kind of level,
+        // or it belongs to the levels of its parent, which are of the other kind
+        let parent_index = (index - 1) / 2;
+        if is_min_level(index) {
+            if self.elements[index] > self.elements[parent_index] {
+                self.elements.swap(index, parent_index);
+                self.bubble_up(parent_index, Ordering::Greater);
+            } else {
+                self.bubble_up(index, Ordering::Less);
+            }
+        } else if self.elements[index] < self.elements[parent_index] {
+            self.elements.swap(index, parent_index);
+            self.bubble_up(parent_index, Ordering::Less);
+        } else {
+            self.bubble_up(index, Ordering::Greater);
+        }
+    }
+
+    pub fn peek_min(&self) -> Option<&T> {
+        self.elements.first()
+    }
+
+    pub fn peek_max(&self) -> Option<&T> {
+        self.max_index().map(|index| &self.elements[index])
+    }
+
+    pub fn pop_min(&mut self) -> Option<T> {
+        if self.elements.is_empty() {
+            return None;
+        }
+
+        let min_element = self.elements.swap_remove(0);
+        self.trickle_down(0);
+        Some(min_element)
+    }
+
+    pub fn pop_max(&mut self) -> Option<T> {
+        let max_index = self.max_index()?;
+
+        let max_element = self.elements.swap_remove(max_index);
+        self.trickle_down(max_index);
+        Some(max_element)
     }
 
     pub fn len(&self) -> usize {
         self.elements.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    fn max_index(&self) -> Option<usize> {
+        match self.elements.len() {
+            0 => None,
+            1 => Some(0),
+            2 => Some(1),
+            _ if self.elements[1] >= self.elements[2] => Some(1),
+            _ => Some(2),
+        }
+    }
+
+    // moves the element up through the grandparents, i.e. along the levels of the same kind,
+    // "order" is Less for min levels and Greater for max levels
+    fn bubble_up(&mut self, mut index: usize, order: Ordering) {
+        while index >= 3 {
+            let grandparent_index = ((index - 1) / 2 - 1) / 2;
+            if self.elements[index].cmp(&self.elements[grandparent_index]) != order {
+                break;
+            }
+            self.elements.swap(index, grandparent_index);
+            index = grandparent_index;
+        }
+    }
+
+    fn trickle_down(&mut self, index: usize) {
+        if index >= self.elements.len() {
+            return;
+        }
+
+        let order = if is_min_level(index) {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        };
+
+        let mut index = index;
+        loop {
+            // the smallest (or biggest on max levels) among children and grandchildren
+            let first_child_index = 2 * index + 1;
+            let descendants = [
+                first_child_index,
+                first_child_index + 1,
+                2 * first_child_index + 1,
+                2 * first_child_index + 2,
+                2 * first_child_index + 3,
+                2 * first_child_index + 4,
+            ];
+            let mut extreme_index = index;
+            for descendant_index in descendants {
+                if descendant_index < self.elements.len()
+                    && self.elements[descendant_index].cmp(&self.elements[extreme_index]) == order
+                {
+                    extreme_index = descendant_index;
+                }
+            }
+
+            if extreme_index == index {
+                // heap property restored, the element is in the right place of its subtree
+                break;
+            }
+
+            self.elements.swap(index, extreme_index);
+            if extreme_index <= first_child_index + 1 {
+                // a child has no descendants on the levels of the same kind below it
+                break;
+            }
+
+            // the swapped element came from the levels of the other kind,
+            // so it might have to be exchanged with its new parent
+            let parent_index = (extreme_index - 1) / 2;
+            if self.elements[extreme_index].cmp(&self.elements[parent_index]) == order.reverse() {
+                self.elements.swap(extreme_index, parent_index);
+            }
+            index = extreme_index;
+        }
+    }
+}
+
+impl<T: Ord> Default for MinMaxHeap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_min_level(index: usize) -> bool {
+    (index + 1).ilog2().is_multiple_of(2)
+}
+
+struct StableEntry<P: Ord, T> {
+    priority: P,
+    sequence: u64,
+    value: T,
+}
+
+impl<P: Ord, T> PartialEq for StableEntry<P, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority && self.sequence == other.sequence
+    }
+}
+
+impl<P: Ord, T> Eq for StableEntry<P, T> {}
+
+impl<P: Ord, T> PartialOrd for StableEntry<P, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<P: Ord, T> Ord for StableEntry<P, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then(self.sequence.cmp(&other.sequence))
+    }
+}
+
+/// Min priority queue which is stable: elements with equal priorities are popped in the
+/// order they were pushed (FIFO).
+///
+/// `MinHeap` makes no promise about the order of equal elements, so every element is paired
+/// with a sequence number of its insertion and the heap is ordered by `(priority, sequence)`.
+/// Use `std::cmp::Reverse` priorities for a max priority queue, equal priorities are still
+/// popped in insertion order.
+pub struct StablePriorityQueue<P: Ord, T> {
+    heap: MinHeap<StableEntry<P, T>>,
+    next_sequence: u64,
+}
+
+impl<P: Ord, T> StablePriorityQueue<P, T> {
+    pub fn new() -> Self {
+        StablePriorityQueue {
+            heap: MinHeap::new(),
+            next_sequence: 0,
+        }
+    }
+
+    pub fn push(&mut self, priority: P, value: T) {
+        self.heap.insert(StableEntry {
+            priority,
+            sequence: self.next_sequence,
+            value,
+        });
+        self.next_sequence += 1;
+    }
+
+    /// Removes the element with the smallest priority, the earliest pushed one among equals.
+    pub fn pop(&mut self) -> Option<(P, T)> {
+        self.heap
+            .extract_min()
+            .map(|entry| (entry.priority, entry.value))
+    }
+
+    pub fn peek(&self) -> Option<(&P, &T)> {
+        self.heap
+            .peek_min()
+            .map(|entry| (&entry.priority, &entry.value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+}
+
+impl<P: Ord, T> Default for StablePriorityQueue<P, T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 pub fn heap_sort<T: Display + PartialOrd + Ord + Copy>(arr: &[T]) -> Vec<T> {
@@ -241,96 +509,364 @@ pub fn heap_sort<T: Display + PartialOrd + Ord + Copy>(arr: &[T]) -> Vec<T> {
     new_arr
 }
 
-pub fn compute_median_stream(arr: &[u64]) -> Vec<u64> {
-    if arr.is_empty() {
-        return vec![];
-    } else if arr.len() == 1 {
-        return vec![arr[0]];
+/// Which element to report as the median when a stream holds an even number of values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MedianPolicy {
+    /// The smaller of the two middle elements.
+    Lower,
+    /// The bigger of the two middle elements.
+    Upper,
+    /// The midpoint of the two middle elements, for [`Midpoint`] values only.
+    Average,
+}
+
+/// The middle element to report as the median of an even number of values, the policies
+/// which work for any `Ord` values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MiddleElement {
+    Lower,
+    Upper,
+}
+
+impl From<MiddleElement> for MedianPolicy {
+    fn from(middle: MiddleElement) -> Self {
+        match middle {
+            MiddleElement::Lower => MedianPolicy::Lower,
+            MiddleElement::Upper => MedianPolicy::Upper,
+        }
     }
-    // Invariant 1: both h1 and h2 are balanced (same lengths, or one is 1 more than the other at max)
-    // Invariant 2: every element of h1 is smaller than every element of h2
-    let mut h1 = MaxHeap::<u64>::new();
-    let mut h2 = MinHeap::<u64>::new();
+}
 
-    let mut medians = Vec::<u64>::with_capacity(arr.len());
+// how the median is picked from both middle elements, averaging them only for Midpoint values
+pub(crate) enum MedianPick<T> {
+    Lower,
+    Upper,
+    Average(fn(&T, &T) -> T),
+}
 
-    if arr[0] < arr[1] {
-        h1.insert(arr[0]);
-        h2.insert(arr[1]);
-        medians.push(arr[0]);
-        medians.push(arr[0]);
-    } else {
-        h1.insert(arr[1]);
-        h2.insert(arr[0]);
-        medians.push(arr[0]);
-        medians.push(arr[1]);
+impl<T> MedianPick<T> {
+    pub(crate) fn policy(&self) -> MedianPolicy {
+        match self {
+            MedianPick::Lower => MedianPolicy::Lower,
+            MedianPick::Upper => MedianPolicy::Upper,
+            MedianPick::Average(_) => MedianPolicy::Average,
+        }
+    }
+
+    pub(crate) fn pick(&self, lower: &T, upper: &T) -> T
+    where
+        T: Clone,
+    {
+        match self {
+            MedianPick::Lower => lower.clone(),
+            MedianPick::Upper => upper.clone(),
+            MedianPick::Average(midpoint) => midpoint(lower, upper),
+        }
+    }
+}
+
+impl<T> From<MiddleElement> for MedianPick<T> {
+    fn from(middle: MiddleElement) -> Self {
+        match middle {
+            MiddleElement::Lower => MedianPick::Lower,
+            MiddleElement::Upper => MedianPick::Upper,
+        }
     }
+}
 
-    // println!("index=0, item={}", arr[0]);
-    // println!("index=1, item={}", arr[1]);
+/// Midpoint of two values, used by [`MedianPolicy::Average`].
+pub trait Midpoint: Clone {
+    /// Returns the value halfway between `low` and `high`, where `low <= high`.
+    fn midpoint(low: &Self, high: &Self) -> Self;
+}
 
-    for index in 2..arr.len() {
-        let item = arr[index];
-        // println!("index={}, item={}", index, item);
+macro_rules! impl_midpoint {
+    ($($t:ty),*) => {
+        $(
+            impl Midpoint for $t {
+                fn midpoint(low: &Self, high: &Self) -> Self {
+                    // low <= high, so the difference never overflows for unsigned types and
+                    // the halved difference always fits back for signed ones
+                    let half = (*high as i128 - *low as i128) / 2;
+                    (*low as i128 + half) as $t
+                }
+            }
+        )*
+    };
+}
 
-        let h1_max = h1.get_max().unwrap();
-        let h2_min = h2.get_min().unwrap();
+impl_midpoint!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+/// Median of a stream of values which arrive one at a time.
+///
+/// Keeps the smaller half of the values in a max heap and the bigger half in a min heap:
+/// - every element of `lower` is smaller than or equal to every element of `upper`
+/// - `lower` holds either the same number of elements as `upper`, or exactly one more
+///
+/// so the middle elements are always at the roots of both heaps.
+pub struct RunningMedian<T: Ord> {
+    lower: MaxHeap<T>,
+    upper: MinHeap<T>,
+    pick: MedianPick<T>,
+}
 
-        if item < h1_max {
-            h1.insert(item);
-        } else if item > h2_min {
-            h2.insert(item);
-        } else {
-            h1.insert(item);
-        }
-
-        let h1_len: i64 = h1.len() as i64;
-        let h2_len: i64 = h2.len() as i64;
-        if (h1_len - h2_len) > 1 {
-            let h1_max = h1.extract_max().unwrap();
-            h2.insert(h1_max);
-        } else if (h2_len - h1_len) > 1 {
-            let h2_min = h2.extract_min().unwrap();
-            h1.insert(h2_min);
-        }
-
-        let mut median;
-        if h1.len() == h2.len() {
-            let h1_max = h1.get_max().unwrap();
-            // let h2_min = h2.extract_min().unwrap();
-            // median = (h1_max + h2_min) / 2;
-            median = h1_max;
-        } else if h1.len() > h2.len() {
-            median = h1.get_max().unwrap();
+impl<T: Ord> RunningMedian<T> {
+    pub fn new() -> Self {
+        Self::with_policy(MiddleElement::Lower)
+    }
+
+    /// Running median reporting the given middle element, use `averaging` for the `Average`
+    /// policy.
+    pub fn with_policy(middle: MiddleElement) -> Self {
+        RunningMedian {
+            lower: MaxHeap::new(),
+            upper: MinHeap::new(),
+            pick: middle.into(),
+        }
+    }
+
+    pub fn policy(&self) -> MedianPolicy {
+        self.pick.policy()
+    }
+
+    /// Adds `value` to the stream in O(log n).
+    pub fn push(&mut self, value: T) {
+        match self.lower.peek_max() {
+            Some(lower_max) if value > *lower_max => self.upper.insert(value),
+            _ => self.lower.insert(value),
+        }
+
+        // restore the balance, a single move is enough as we insert one element at a time
+        if self.lower.len() > self.upper.len() + 1 {
+            let lower_max = self.lower.extract_max().unwrap();
+            self.upper.insert(lower_max);
+        } else if self.upper.len() > self.lower.len() {
+            let upper_min = self.upper.extract_min().unwrap();
+            self.lower.insert(upper_min);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lower.len() + self.upper.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The smaller middle element, or the only middle element if the stream length is odd.
+    pub fn lower_median(&self) -> Option<&T> {
+        self.lower.peek_max()
+    }
+
+    /// The bigger middle element, or the only middle element if the stream length is odd.
+    pub fn upper_median(&self) -> Option<&T> {
+        if self.lower.len() > self.upper.len() {
+            self.lower.peek_max()
         } else {
-            median = h2.get_min().unwrap();
+            self.upper.peek_min()
+        }
+    }
+}
+
+impl<T: Ord + Clone> RunningMedian<T> {
+    /// The median of all values pushed so far according to the policy, computed in O(1).
+    pub fn median(&self) -> Option<T> {
+        let lower = self.lower_median()?;
+        let upper = self.upper_median()?;
+        Some(self.pick.pick(lower, upper))
+    }
+}
+
+impl<T: Ord + Midpoint> RunningMedian<T> {
+    /// Running median of the `Average` policy.
+    pub fn averaging() -> Self {
+        RunningMedian {
+            lower: MaxHeap::new(),
+            upper: MinHeap::new(),
+            pick: MedianPick::Average(T::midpoint),
         }
+    }
 
-        medians.push(median);
+    /// The midpoint of both middle elements whatever the policy.
+    pub fn average_median(&self) -> Option<T> {
+        Some(T::midpoint(self.lower_median()?, self.upper_median()?))
     }
+}
 
-    println!("medians.len()={}", medians.len());
-    println!("medians[..5]={:?}", &medians[..5]);
-    let mut median_sum = 0;
-    for median in &medians {
-        median_sum += median;
+impl<T: Ord> Default for RunningMedian<T> {
+    fn default() -> Self {
+        Self::new()
     }
-    println!("median_sum={}", &median_sum);
-    median_sum = median_sum % 10000;
-    println!("median_sum % 10000={}", &median_sum);
+}
 
-    // let max = h2.extract_max().unwrap();
-    // println!("max={}", max);
-    println!(
-        "h1.len()={}, h1_max_root={}",
-        h1.len(),
-        h1.get_max().unwrap()
-    );
-    println!(
-        "h2.len()={}, h2_min_root={}",
-        h2.len(),
-        h2.get_min().unwrap()
-    );
+/// Returns the (lower) median of every prefix of `arr`.
+pub fn compute_median_stream(arr: &[u64]) -> Vec<u64> {
+    let mut running_median = RunningMedian::with_policy(MiddleElement::Lower);
+    let mut medians = Vec::<u64>::with_capacity(arr.len());
+    for item in arr {
+        running_median.push(*item);
+        medians.push(running_median.median().unwrap());
+    }
 
     medians
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use std::cmp;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_compute_median_stream() {
+        assert_eq!(compute_median_stream(&[]), vec![]);
+        assert_eq!(compute_median_stream(&[7]), vec![7]);
+        assert_eq!(compute_median_stream(&[7, 3]), vec![7, 3]);
+        assert_eq!(compute_median_stream(&[3, 7]), vec![3, 3]);
+        assert_eq!(
+            compute_median_stream(&[5, 15, 1, 3, 2, 8, 7, 9, 10, 6, 11, 4]),
+            vec![5, 5, 5, 3, 3, 3, 5, 5, 7, 6, 7, 6]
+        );
+        assert_eq!(compute_median_stream(&[4, 4, 4, 4]), vec![4, 4, 4, 4]);
+    }
+
+    #[test]
+    fn test_running_median_policies() {
+        let mut lower = RunningMedian::with_policy(MiddleElement::Lower);
+        let mut upper = RunningMedian::with_policy(MiddleElement::Upper);
+        let mut average = RunningMedian::averaging();
+        assert_eq!(lower.median(), None);
+        assert!(average.is_empty());
+        assert_eq!(upper.policy(), MedianPolicy::Upper);
+        assert_eq!(average.policy(), MedianPolicy::Average);
+
+        for value in [10i64, -4, 7, 1] {
+            lower.push(value);
+            upper.push(value);
+            average.push(value);
+        }
+        // sorted: -4, 1, 7, 10
+        assert_eq!(lower.median(), Some(1));
+        assert_eq!(upper.median(), Some(7));
+        assert_eq!(average.median(), Some(4));
+        assert_eq!(lower.average_median(), Some(4));
+
+        average.push(100);
+        assert_eq!(average.median(), Some(7));
+        assert_eq!(average.len(), 5);
+
+        let mut extremes = RunningMedian::averaging();
+        extremes.push(u64::MAX);
+        extremes.push(u64::MAX - 2);
+        assert_eq!(extremes.median(), Some(u64::MAX - 1));
+    }
+
+    #[test]
+    fn test_running_median_any_ord() {
+        let mut running_median = RunningMedian::new();
+        for word in ["pear", "apple", "fig", "banana"] {
+            running_median.push(word.to_string());
+        }
+        assert_eq!(running_median.lower_median().unwrap(), "banana");
+        assert_eq!(running_median.upper_median().unwrap(), "fig");
+        assert_eq!(running_median.median().unwrap(), "banana");
+
+        let mut running_median = RunningMedian::with_policy(MiddleElement::Upper);
+        for word in ["pear", "apple", "fig", "banana"] {
+            running_median.push(word.to_string());
+        }
+        assert_eq!(running_median.median().unwrap(), "fig");
+    }
+
+    #[test]
+    fn test_min_max_heap() {
+        let mut heap = MinMaxHeap::<i32>::new();
+        assert_eq!(heap.peek_min(), None);
+        assert_eq!(heap.pop_max(), None);
+
+        heap.insert(5);
+        assert_eq!(heap.peek_min(), Some(&5));
+        assert_eq!(heap.peek_max(), Some(&5));
+
+        let mut heap = MinMaxHeap::from(&[10, 5, 0, 2, 1, 3, 7, 4, 6, 9, 7, 10, 8]);
+        assert_eq!(heap.len(), 13);
+        assert_eq!(heap.pop_min(), Some(0));
+        assert_eq!(heap.pop_max(), Some(10));
+        assert_eq!(heap.pop_max(), Some(10));
+        assert_eq!(heap.pop_min(), Some(1));
+        assert_eq!(heap.peek_min(), Some(&2));
+        assert_eq!(heap.peek_max(), Some(&9));
+
+        // alternate between both ends of randomly filled heaps and compare with sorted input
+        let mut rng = thread_rng();
+        for _ in 0..200 {
+            let len = rng.gen_range(0..100);
+            let arr: Vec<u32> = (0..len).map(|_| rng.gen_range(0..50)).collect();
+            let mut sorted = arr.clone();
+            sorted.sort();
+            let mut sorted = VecDeque::from(sorted);
+
+            let mut heap = MinMaxHeap::from(&arr);
+            while !heap.is_empty() {
+                if rng.gen_bool(0.5) {
+                    assert_eq!(heap.pop_min(), sorted.pop_front(), "arr={:?}", arr);
+                } else {
+                    assert_eq!(heap.pop_max(), sorted.pop_back(), "arr={:?}", arr);
+                }
+                assert_eq!(heap.peek_min(), sorted.front());
+                assert_eq!(heap.peek_max(), sorted.back());
+                if rng.gen_bool(0.2) {
+                    let value = rng.gen_range(0..50);
+                    heap.insert(value);
+                    let index = sorted.partition_point(|item| *item < value);
+                    sorted.insert(index, value);
+                }
+            }
+            assert!(sorted.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_stable_priority_queue() {
+        let mut queue = StablePriorityQueue::new();
+        assert_eq!(queue.pop(), None);
+
+        for (priority, value) in [(2, "a"), (1, "b"), (2, "c"), (1, "d"), (0, "e"), (2, "f")] {
+            queue.push(priority, value);
+        }
+        assert_eq!(queue.len(), 6);
+        assert_eq!(queue.peek(), Some((&0, &"e")));
+
+        let mut popped = Vec::new();
+        while let Some((_, value)) = queue.pop() {
+            popped.push(value);
+        }
+        assert_eq!(popped, vec!["e", "b", "d", "a", "c", "f"]);
+
+        // many equal priorities pushed between pops still come out in insertion order
+        let mut rng = thread_rng();
+        let mut queue = StablePriorityQueue::new();
+        let mut expected: Vec<(u8, usize)> = Vec::new();
+        for index in 0..1000 {
+            let priority = rng.gen_range(0..4);
+            queue.push(priority, index);
+            expected.push((priority, index));
+        }
+        expected.sort_by_key(|(priority, _)| *priority);
+        let mut popped = Vec::new();
+        while let Some(entry) = queue.pop() {
+            popped.push(entry);
+        }
+        assert_eq!(popped, expected);
+
+        let mut queue = StablePriorityQueue::new();
+        for (priority, value) in [(1, 'x'), (3, 'y'), (3, 'z'), (1, 'w')] {
+            queue.push(cmp::Reverse(priority), value);
+        }
+        let popped: Vec<char> =
+            std::iter::from_fn(|| queue.pop().map(|(_, value)| value)).collect();
+        assert_eq!(popped, vec!['y', 'z', 'x', 'w']);
+    }
+}
@@ -1,74 +1,1570 @@
+use crate::bitstream::{BitReader, BitWriter};
+use crate::entropy::{EntropyCoder, FrequencyModel};
 use crate::heap;
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::iter::FusedIterator;
+use std::{cmp, fmt};
 
-#[derive(Debug)]
-pub struct BinaryTree<T: Ord> {
+/// Binary tree owning its subtrees.
+///
+/// Trees compare by the values of their roots alone, which makes them usable as heap entries
+/// keyed by those values.
+#[derive(Debug, Clone)]
+pub struct BinaryTree<T> {
     pub value: T,
     pub left: Option<Box<BinaryTree<T>>>,
     pub right: Option<Box<BinaryTree<T>>>,
 }
 
-impl PartialEq for BinaryTree<usize> {
-    fn eq(&self, other: &BinaryTree<usize>) -> bool {
+impl<T: PartialEq> PartialEq for BinaryTree<T> {
+    fn eq(&self, other: &BinaryTree<T>) -> bool {
         self.value == other.value
     }
 }
 
-impl Eq for BinaryTree<usize> {}
+impl<T: Eq> Eq for BinaryTree<T> {}
 
-impl PartialOrd for BinaryTree<usize> {
-    fn partial_cmp(&self, other: &BinaryTree<usize>) -> Option<Ordering> {
+impl<T: PartialOrd> PartialOrd for BinaryTree<T> {
+    fn partial_cmp(&self, other: &BinaryTree<T>) -> Option<Ordering> {
         self.value.partial_cmp(&other.value)
     }
 }
 
-impl Ord for BinaryTree<usize> {
-    fn cmp(&self, other: &BinaryTree<usize>) -> Ordering {
+impl<T: Ord> Ord for BinaryTree<T> {
+    fn cmp(&self, other: &BinaryTree<T>) -> Ordering {
         self.value.cmp(&other.value)
     }
 }
 
-pub fn compute_code_tree(frequencies: &[usize]) -> BinaryTree<usize> {
-    let mut heap = heap::MinHeap::<usize>::new();
-    let mut forest = HashMap::<usize, BinaryTree<usize>>::with_capacity(frequencies.len());
-    for frequency in frequencies {
-        forest.insert(
-            *frequency,
-            BinaryTree {
-                value: *frequency,
-                left: None,
-                right: None,
+impl<T> BinaryTree<T> {
+    pub fn leaf(value: T) -> Self {
+        BinaryTree {
+            value,
+            left: None,
+            right: None,
+        }
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        self.left.is_none() && self.right.is_none()
+    }
+
+    /// Values in pre-order: a node, then its left subtree, then its right subtree.
+    pub fn pre_order(&self) -> PreOrder<'_, T> {
+        PreOrder { stack: vec![self] }
+    }
+
+    /// Values in in-order: the left subtree of a node, then the node, then its right subtree.
+    pub fn in_order(&self) -> InOrder<'_, T> {
+        InOrder {
+            stack: Vec::new(),
+            current: Some(self),
+        }
+    }
+
+    /// Values in post-order: the left subtree of a node, then its right subtree, then the node.
+    pub fn post_order(&self) -> PostOrder<'_, T> {
+        PostOrder {
+            stack: vec![(self, false)],
+        }
+    }
+
+    /// Values level by level from the root, each level from left to right.
+    pub fn level_order(&self) -> LevelOrder<'_, T> {
+        LevelOrder {
+            queue: VecDeque::from([self]),
+        }
+    }
+
+    /// Number of edges on the longest path from the root to a leaf, 0 for a single node.
+    pub fn height(&self) -> usize {
+        let mut height = 0;
+        let mut stack = vec![(self, 0)];
+        while let Some((tree, depth)) = stack.pop() {
+            height = cmp::max(height, depth);
+            for child in [&tree.left, &tree.right].into_iter().flatten() {
+                stack.push((child, depth + 1));
+            }
+        }
+        height
+    }
+
+    /// Number of nodes.
+    pub fn size(&self) -> usize {
+        self.pre_order().count()
+    }
+
+    /// Values of the leaves from left to right.
+    pub fn leaves(&self) -> impl Iterator<Item = &T> {
+        self.subtrees_pre_order()
+            .filter(|tree| tree.is_leaf())
+            .map(|tree| &tree.value)
+    }
+
+    /// Tree of the same shape with `f` applied to every value, in pre-order.
+    pub fn map<U, F: FnMut(&T) -> U>(&self, mut f: F) -> BinaryTree<U> {
+        self.map_with(&mut f)
+    }
+
+    fn map_with<U, F: FnMut(&T) -> U>(&self, f: &mut F) -> BinaryTree<U> {
+        let value = f(&self.value);
+        BinaryTree {
+            value,
+            left: self.left.as_ref().map(|left| Box::new(left.map_with(f))),
+            right: self.right.as_ref().map(|right| Box::new(right.map_with(f))),
+        }
+    }
+
+    /// Every leaf from left to right with the path to it from the root, `false` for going to a
+    /// left child and `true` for going to a right child, i.e. the codeword of the leaf.
+    pub fn paths_to_leaves(&self) -> Vec<(Vec<bool>, &T)> {
+        let mut paths = Vec::new();
+        let mut stack = vec![(self, Vec::new())];
+        while let Some((tree, path)) = stack.pop() {
+            if tree.is_leaf() {
+                paths.push((path, &tree.value));
+                continue;
+            }
+            for (child, bit) in [(&tree.right, true), (&tree.left, false)] {
+                if let Some(child) = child {
+                    let mut child_path = path.clone();
+                    child_path.push(bit);
+                    stack.push((child, child_path));
+                }
+            }
+        }
+        paths
+    }
+
+    fn subtrees_pre_order(&self) -> impl Iterator<Item = &BinaryTree<T>> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let tree = stack.pop()?;
+            stack.extend(tree.right.as_deref());
+            stack.extend(tree.left.as_deref());
+            Some(tree)
+        })
+    }
+}
+
+pub struct PreOrder<'a, T> {
+    stack: Vec<&'a BinaryTree<T>>,
+}
+
+impl<'a, T> Iterator for PreOrder<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let tree = self.stack.pop()?;
+        self.stack.extend(tree.right.as_deref());
+        self.stack.extend(tree.left.as_deref());
+        Some(&tree.value)
+    }
+}
+
+impl<T> FusedIterator for PreOrder<'_, T> {}
+
+pub struct InOrder<'a, T> {
+    // ancestors whose left subtree is being visited
+    stack: Vec<&'a BinaryTree<T>>,
+    // root of the next subtree to visit
+    current: Option<&'a BinaryTree<T>>,
+}
+
+impl<'a, T> Iterator for InOrder<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        while let Some(tree) = self.current {
+            self.stack.push(tree);
+            self.current = tree.left.as_deref();
+        }
+        let tree = self.stack.pop()?;
+        self.current = tree.right.as_deref();
+        Some(&tree.value)
+    }
+}
+
+impl<T> FusedIterator for InOrder<'_, T> {}
+
+pub struct PostOrder<'a, T> {
+    // nodes paired with whether their children are already on the stack
+    stack: Vec<(&'a BinaryTree<T>, bool)>,
+}
+
+impl<'a, T> Iterator for PostOrder<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        loop {
+            let (tree, expanded) = self.stack.pop()?;
+            if expanded {
+                return Some(&tree.value);
+            }
+            self.stack.push((tree, true));
+            if let Some(right) = &tree.right {
+                self.stack.push((right, false));
+            }
+            if let Some(left) = &tree.left {
+                self.stack.push((left, false));
+            }
+        }
+    }
+}
+
+impl<T> FusedIterator for PostOrder<'_, T> {}
+
+pub struct LevelOrder<'a, T> {
+    queue: VecDeque<&'a BinaryTree<T>>,
+}
+
+impl<'a, T> Iterator for LevelOrder<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let tree = self.queue.pop_front()?;
+        self.queue.extend(tree.left.as_deref());
+        self.queue.extend(tree.right.as_deref());
+        Some(&tree.value)
+    }
+}
+
+impl<T> FusedIterator for LevelOrder<'_, T> {}
+
+/// Node of a code tree of labelled symbols: the summed weight of the subtree and, for leaves,
+/// the symbol id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CodeNode {
+    pub weight: usize,
+    pub symbol: Option<usize>,
+}
+
+impl fmt::Display for CodeNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.symbol {
+            Some(symbol) => write!(f, "{}: {}", symbol, self.weight),
+            None => write!(f, "{}", self.weight),
+        }
+    }
+}
+
+/// Label of a node of a tree of byte symbols, printable ASCII bytes shown as characters.
+pub fn byte_node_label(node: &CodeNode) -> String {
+    match node.symbol {
+        Some(symbol) if (0x21..0x7f).contains(&symbol) => {
+            format!("'{}': {}", symbol as u8 as char, node.weight)
+        }
+        Some(symbol) => format!("0x{:02x}: {}", symbol, node.weight),
+        None => node.weight.to_string(),
+    }
+}
+
+/// Builds the Huffman code tree of the symbols with non zero frequencies, the symbol id of
+/// every leaf is the index of its frequency.
+///
+/// Ties are broken deterministically, so the tree is the same for the same frequencies: among
+/// trees of equal weights the ones pushed into the heap earlier are merged first, i.e. leaves
+/// in the order of their symbol ids, then merged trees in the order they were created. The
+/// first of the two merged trees becomes the left child.
+///
+/// Panics if no symbol has a positive frequency.
+pub fn compute_code_tree(frequencies: &[usize]) -> BinaryTree<CodeNode> {
+    let leaves = frequencies.iter().enumerate().map(|(symbol, frequency)| {
+        BinaryTree::leaf(CodeNode {
+            weight: *frequency,
+            symbol: Some(symbol),
+        })
+    });
+    merge_lightest(leaves, 2, 2, |weight, children| {
+        let [left, right]: [BinaryTree<CodeNode>; 2] = children.try_into().unwrap();
+        BinaryTree {
+            value: CodeNode {
+                weight,
+                symbol: None,
+            },
+            left: Some(Box::new(left)),
+            right: Some(Box::new(right)),
+        }
+    })
+    .expect("At least one symbol should have a positive frequency")
+}
+
+// Huffman merging of the trees of positive weights: `first` lightest trees are merged first, then
+// `arity` at a time until a single tree is left. `merge` receives the summed weight and the
+// merged trees, lightest first. Ties go to the trees pushed into the heap earlier.
+fn merge_lightest<T, I, F>(trees: I, first: usize, arity: usize, mut merge: F) -> Option<T>
+where
+    T: Weighted,
+    I: IntoIterator<Item = T>,
+    F: FnMut(usize, Vec<T>) -> T,
+{
+    let mut heap = heap::StablePriorityQueue::<usize, T>::new();
+    for tree in trees {
+        if tree.weight() > 0 {
+            heap.push(tree.weight(), tree);
+        }
+    }
+
+    let mut group = first;
+    while heap.len() > 1 {
+        let mut merged_weight = 0;
+        let mut children = Vec::with_capacity(group);
+        for _ in 0..group {
+            let (weight, tree) = heap.pop().unwrap();
+            merged_weight += weight;
+            children.push(tree);
+        }
+        heap.push(merged_weight, merge(merged_weight, children));
+        group = arity;
+    }
+
+    // return unique tree remaining in the heap
+    heap.pop().map(|(_, tree)| tree)
+}
+
+trait Weighted {
+    fn weight(&self) -> usize;
+}
+
+impl Weighted for BinaryTree<CodeNode> {
+    fn weight(&self) -> usize {
+        self.value.weight
+    }
+}
+
+/// Code tree of a `arity`-ary prefix code, every internal node having up to `arity` children.
+/// The child at index `i` stands for the digit `i`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DaryCodeTree {
+    pub value: CodeNode,
+    pub children: Vec<DaryCodeTree>,
+}
+
+impl Weighted for DaryCodeTree {
+    fn weight(&self) -> usize {
+        self.value.weight
+    }
+}
+
+/// Builds the optimal prefix code tree over an alphabet of `arity` digits of the symbols with
+/// non zero frequencies, generalizing `compute_code_tree` which is the case of arity 2.
+///
+/// Merging `arity` trees at a time leaves the root with fewer children unless the number of
+/// symbols `n` satisfies `(n - 1) % (arity - 1) == 0`, and a short root wastes the shortest
+/// codewords. The tree is built as if padded with dummy symbols of weight 0 up to such a count:
+/// the dummies are the lightest, so they are merged first, which is what the first merge of
+/// only `2 + (n - 2) % (arity - 1)` real trees amounts to. Only the deepest internal node then
+/// has fewer than `arity` children.
+///
+/// Panics if `arity` isn't in `2..=256` or no symbol has a positive frequency.
+pub fn compute_dary_code_tree(frequencies: &[usize], arity: usize) -> DaryCodeTree {
+    assert!(
+        (2..=256).contains(&arity),
+        "Expected an arity of 2 to 256, got {}",
+        arity
+    );
+    let symbols = frequencies
+        .iter()
+        .filter(|frequency| **frequency > 0)
+        .count();
+    let first = if symbols < 2 {
+        2
+    } else {
+        2 + (symbols - 2) % (arity - 1)
+    };
+    let leaves = frequencies
+        .iter()
+        .enumerate()
+        .map(|(symbol, frequency)| DaryCodeTree {
+            value: CodeNode {
+                weight: *frequency,
+                symbol: Some(symbol),
             },
+            children: Vec::new(),
+        });
+    merge_lightest(leaves, first, arity, |weight, children| DaryCodeTree {
+        value: CodeNode {
+            weight,
+            symbol: None,
+        },
+        children,
+    })
+    .expect("At least one symbol should have a positive frequency")
+}
+
+/// Derives the digits of the codeword of every symbol of the tree, indexed by symbol id like
+/// `code_table`. A tree of a single leaf gives its symbol an empty codeword.
+pub fn dary_code_table(code_tree: &DaryCodeTree) -> Vec<Option<Vec<u8>>> {
+    let mut codes = Vec::new();
+    let mut stack = vec![(code_tree, Vec::new())];
+    while let Some((tree, code)) = stack.pop() {
+        if let Some(symbol) = tree.value.symbol {
+            if codes.len() <= symbol {
+                codes.resize(symbol + 1, None);
+            }
+            codes[symbol] = Some(code);
+            continue;
+        }
+        for (digit, child) in tree.children.iter().enumerate() {
+            let mut child_code = code.clone();
+            child_code.push(digit as u8);
+            stack.push((child, child_code));
+        }
+    }
+    codes
+}
+
+/// Decodes `len` symbols from the concatenated codeword digits of the tree.
+pub fn dary_decode(
+    code_tree: &DaryCodeTree,
+    digits: &[u8],
+    len: usize,
+) -> Result<Vec<usize>, DecodeError> {
+    if let Some(symbol) = code_tree.value.symbol {
+        if len > MAX_DECODED_LEN {
+            return Err(DecodeError::TooLong);
+        }
+        return Ok(vec![symbol; len]);
+    }
+
+    // every codeword takes a digit at least
+    if len > digits.len() {
+        return Err(DecodeError::UnexpectedEnd);
+    }
+    let mut symbols = Vec::with_capacity(len);
+    let mut digits = digits.iter();
+    while symbols.len() < len {
+        let mut tree = code_tree;
+        while tree.value.symbol.is_none() {
+            let digit = digits.next().ok_or(DecodeError::UnexpectedEnd)?;
+            tree = tree
+                .children
+                .get(*digit as usize)
+                .ok_or(DecodeError::CorruptedData)?;
+        }
+        symbols.push(tree.value.symbol.unwrap());
+    }
+    Ok(symbols)
+}
+
+/// Statistics of a code tree, the weights of the leaves being the frequencies of the symbols.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CodeTreeStats {
+    pub symbols: usize,
+    pub min_codeword_len: usize,
+    pub max_codeword_len: usize,
+    /// Mean codeword length over the symbols, regardless of their frequencies.
+    pub average_codeword_len: f64,
+    /// Mean codeword length weighted by the symbol frequencies, i.e. bits per encoded symbol.
+    pub weighted_average_len: f64,
+    /// Shannon entropy of the symbol frequencies in bits, the lower bound of
+    /// `weighted_average_len` for any prefix code.
+    pub entropy: f64,
+    /// `entropy / weighted_average_len`, 1.0 for a code which can't be improved.
+    pub efficiency: f64,
+}
+
+impl BinaryTree<CodeNode> {
+    /// Computes the codeword statistics with a breadth-first traversal of the tree.
+    pub fn stats(&self) -> CodeTreeStats {
+        let mut symbols = 0;
+        let mut min_codeword_len = usize::MAX;
+        let mut max_codeword_len = 0;
+        let mut total_len = 0;
+        let mut total_weight = 0;
+        let mut weighted_total_len = 0;
+        let mut weights = Vec::new();
+
+        // create a queue with the root as a starting point, every tree is paired with the
+        // length of the codeword of its root
+        let mut deq = VecDeque::from([(self, 0)]);
+        while let Some((tree, code_length)) = deq.pop_front() {
+            // end leaf of a tree, so we need to record its codeword length
+            if tree.left.is_none() && tree.right.is_none() {
+                symbols += 1;
+                min_codeword_len = cmp::min(min_codeword_len, code_length);
+                max_codeword_len = cmp::max(max_codeword_len, code_length);
+                total_len += code_length;
+                total_weight += tree.value.weight;
+                weighted_total_len += tree.value.weight * code_length;
+                weights.push(tree.value.weight);
+            }
+
+            if let Some(node) = &tree.left {
+                deq.push_back((node, code_length + 1));
+            }
+            if let Some(node) = &tree.right {
+                deq.push_back((node, code_length + 1));
+            }
+        }
+
+        let weighted_average_len = if total_weight == 0 {
+            0.0
+        } else {
+            weighted_total_len as f64 / total_weight as f64
+        };
+        let entropy = weights
+            .iter()
+            .filter(|weight| **weight > 0)
+            .map(|weight| {
+                let p = *weight as f64 / total_weight as f64;
+                -p * p.log2()
+            })
+            .sum();
+        let efficiency = if weighted_average_len == 0.0 {
+            1.0
+        } else {
+            entropy / weighted_average_len
+        };
+
+        CodeTreeStats {
+            symbols,
+            min_codeword_len,
+            max_codeword_len,
+            average_codeword_len: total_len as f64 / symbols as f64,
+            weighted_average_len,
+            entropy,
+            efficiency,
+        }
+    }
+}
+
+impl<T: fmt::Display> BinaryTree<T> {
+    /// Renders the tree as a Graphviz DOT digraph, see `to_dot_with`.
+    pub fn to_dot(&self) -> String {
+        self.to_dot_with(|value| value.to_string())
+    }
+
+    /// Renders the tree as an indented ASCII tree, see `to_ascii_with`.
+    pub fn to_ascii(&self) -> String {
+        self.to_ascii_with(|value| value.to_string())
+    }
+}
+
+impl<T> BinaryTree<T> {
+    /// Renders the tree as a Graphviz DOT digraph with the nodes labelled by `label`. Edges to
+    /// left children are labelled 0 and to right children 1, like the bits of the codewords,
+    /// and leaves are drawn as boxes.
+    pub fn to_dot_with<F: Fn(&T) -> String>(&self, label: F) -> String {
+        let mut dot = String::from("digraph {\n");
+        let mut next_id = 0;
+        self.write_dot(&label, &mut dot, &mut next_id);
+        dot.push_str("}\n");
+        dot
+    }
+
+    // writes the subtree with node ids from next_id on, returns the id of its root
+    fn write_dot<F: Fn(&T) -> String>(
+        &self,
+        label: &F,
+        dot: &mut String,
+        next_id: &mut usize,
+    ) -> usize {
+        let id = *next_id;
+        *next_id += 1;
+        let escaped = label(&self.value)
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"");
+        let shape = if self.left.is_none() && self.right.is_none() {
+            "box"
+        } else {
+            "ellipse"
+        };
+        dot.push_str(&format!(
+            "    n{} [label=\"{}\", shape={}];\n",
+            id, escaped, shape
+        ));
+        for (child, bit) in [(&self.left, 0), (&self.right, 1)] {
+            if let Some(child) = child {
+                let child_id = child.write_dot(label, dot, next_id);
+                dot.push_str(&format!(
+                    "    n{} -> n{} [label=\"{}\"];\n",
+                    id, child_id, bit
+                ));
+            }
+        }
+        id
+    }
+
+    /// Renders the tree with a line per node labelled by `label`, children indented below their
+    /// parent and prefixed by the bit of their edge, e.g. for `compute_code_tree(&[5, 2, 2])`
+    ///
+    /// ```text
+    /// 9
+    /// ├─0─ 4
+    /// │   ├─0─ 1: 2
+    /// │   └─1─ 2: 2
+    /// └─1─ 0: 5
+    /// ```
+    pub fn to_ascii_with<F: Fn(&T) -> String>(&self, label: F) -> String {
+        let mut ascii = label(&self.value);
+        ascii.push('\n');
+        self.write_ascii_children(&label, "", &mut ascii);
+        ascii
+    }
+
+    fn write_ascii_children<F: Fn(&T) -> String>(
+        &self,
+        label: &F,
+        indent: &str,
+        ascii: &mut String,
+    ) {
+        let children: Vec<_> = [(&self.left, 0), (&self.right, 1)]
+            .into_iter()
+            .filter_map(|(child, bit)| child.as_deref().map(|child| (child, bit)))
+            .collect();
+        for (i, (child, bit)) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            let branch = if last { "└" } else { "├" };
+            ascii.push_str(&format!(
+                "{}{}─{}─ {}\n",
+                indent,
+                branch,
+                bit,
+                label(&child.value)
+            ));
+            let child_indent = format!("{}{}", indent, if last { "    " } else { "│   " });
+            child.write_ascii_children(label, &child_indent, ascii);
+        }
+    }
+}
+
+/// Codeword of a symbol, `len` bits stored in the lowest bits of `bits`, the first bit of the
+/// code being the highest of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Codeword {
+    pub bits: u64,
+    pub len: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended before all the symbols were decoded.
+    UnexpectedEnd,
+    /// The serialized code tree is malformed.
+    InvalidCodeTree,
+    /// The encoded data doesn't decode to the expected number of symbols.
+    CorruptedData,
+    /// The length to decode is above `MAX_DECODED_LEN`.
+    TooLong,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd => write!(f, "unexpected end of the encoded data"),
+            DecodeError::InvalidCodeTree => write!(f, "invalid code tree in the header"),
+            DecodeError::CorruptedData => write!(f, "corrupted encoded data"),
+            DecodeError::TooLong => write!(
+                f,
+                "decoded length above the limit of {} symbols",
+                MAX_DECODED_LEN
+            ),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+/// Counts the occurrences of every byte value in `data`.
+pub fn byte_frequencies(data: &[u8]) -> Vec<usize> {
+    let mut frequencies = vec![0; 256];
+    for byte in data {
+        frequencies[*byte as usize] += 1;
+    }
+    frequencies
+}
+
+/// Derives the codeword of every symbol of the tree, going left appends 0 and going right
+/// appends 1. The table is indexed by symbol id, symbols missing from the tree have no codeword.
+///
+/// A tree of a single leaf gives its symbol an empty codeword.
+pub fn code_table(code_tree: &BinaryTree<CodeNode>) -> Vec<Option<Codeword>> {
+    let mut codes = Vec::new();
+    let mut stack = vec![(code_tree, Codeword { bits: 0, len: 0 })];
+    while let Some((tree, code)) = stack.pop() {
+        if let Some(symbol) = tree.value.symbol {
+            if codes.len() <= symbol {
+                codes.resize(symbol + 1, None);
+            }
+            codes[symbol] = Some(code);
+            continue;
+        }
+
+        assert!(
+            code.len < 64,
+            "Codewords longer than 64 bits are not supported"
         );
-        heap.insert(*frequency);
+        if let Some(left) = &tree.left {
+            let left_code = Codeword {
+                bits: code.bits << 1,
+                len: code.len + 1,
+            };
+            stack.push((left, left_code));
+        }
+        if let Some(right) = &tree.right {
+            let right_code = Codeword {
+                bits: (code.bits << 1) | 1,
+                len: code.len + 1,
+            };
+            stack.push((right, right_code));
+        }
     }
-    // forest.sort_by(|a, b| b.value.cmp(&a.value));
-    // println!("forst = {:?}", forest);
-    assert_eq!(heap.len(), forest.len());
 
-    while forest.len() > 1 {
-        // println!("forest.len() = {}, heap.len() = {}", forest.len(), heap.len());
+    codes
+}
+
+/// Code length of every symbol of the tree, 0 for symbols missing from it.
+///
+/// The only symbol of a single leaf tree gets a length of 1, as a length of 0 means that the
+/// symbol has no codeword.
+pub fn code_lengths(code_tree: &BinaryTree<CodeNode>) -> Vec<u32> {
+    code_table(code_tree)
+        .iter()
+        .map(|code| match code {
+            Some(code) => cmp::max(code.len, 1),
+            None => 0,
+        })
+        .collect()
+}
 
-        let min_freq = heap.extract_min().unwrap();
-        // println!("min_freq={}", min_freq);
-        let min_freq_tree = forest.remove(&min_freq).unwrap();
+/// Assigns canonical codewords to symbols of the given code lengths (as in DEFLATE, RFC 1951).
+///
+/// Shorter codes come first, and codes of the same length are consecutive binary numbers in
+/// the order of symbol ids, so the code is fully described by the lengths alone. Symbols of
+/// length 0 have no codeword.
+///
+/// Panics if the lengths don't satisfy the Kraft inequality, i.e. no prefix code has them.
+pub fn canonical_code_table(lengths: &[u32]) -> Vec<Option<Codeword>> {
+    let max_len = lengths.iter().copied().max().unwrap_or(0);
+    assert!(
+        max_len <= 64,
+        "Codewords longer than 64 bits are not supported"
+    );
 
-        let second_min_freq = heap.extract_min().unwrap();
-        // println!("second_min_freq={}", second_min_freq);
-        let second_min_freq_tree = forest.remove(&second_min_freq).unwrap();
+    // number of codes of every length
+    let mut length_counts = vec![0u64; max_len as usize + 1];
+    for len in lengths {
+        if *len > 0 {
+            length_counts[*len as usize] += 1;
+        }
+    }
 
-        let merged_frequency = min_freq_tree.value + second_min_freq_tree.value;
-        let merged_tree = BinaryTree {
-            value: merged_frequency,
-            left: Some(Box::new(min_freq_tree)),
-            right: Some(Box::new(second_min_freq_tree)),
+    // the first code of every length follows the last code of the previous length,
+    // extended by one bit
+    let mut next_code = vec![0u64; max_len as usize + 1];
+    let mut code = 0u64;
+    for len in 1..=max_len as usize {
+        code = (code + length_counts[len - 1]) << 1;
+        // the codes of this length have to fit into "len" bits
+        assert!(
+            len == 64 || code + length_counts[len] <= 1 << len,
+            "Code lengths don't satisfy the Kraft inequality"
+        );
+        next_code[len] = code;
+    }
+
+    lengths
+        .iter()
+        .map(|len| {
+            if *len == 0 {
+                return None;
+            }
+            let bits = next_code[*len as usize];
+            next_code[*len as usize] += 1;
+            Some(Codeword { bits, len: *len })
+        })
+        .collect()
+}
+
+/// Rebuilds the code tree of a code table, e.g. of a canonical code, for decoding. The leaves
+/// carry symbol ids but no weights.
+///
+/// Returns `None` if the codes are not a prefix code. Codeword paths of an incomplete code
+/// (lengths with a Kraft sum below 1) end in internal nodes without one of the children.
+pub fn code_tree_from_codes(codes: &[Option<Codeword>]) -> Option<BinaryTree<CodeNode>> {
+    let internal = CodeNode {
+        weight: 0,
+        symbol: None,
+    };
+    let mut root = BinaryTree {
+        value: internal,
+        left: None,
+        right: None,
+    };
+
+    for (symbol, code) in codes.iter().enumerate() {
+        let code = match code {
+            Some(code) => code,
+            None => continue,
         };
-        heap.insert(merged_frequency);
-        forest.insert(merged_frequency, merged_tree);
+
+        let mut tree = &mut root;
+        for shift in (0..code.len).rev() {
+            // a codeword can't pass through the leaf of another one
+            if tree.value.symbol.is_some() {
+                return None;
+            }
+            let child = if (code.bits >> shift) & 1 == 1 {
+                &mut tree.right
+            } else {
+                &mut tree.left
+            };
+            tree = child.get_or_insert_with(|| {
+                Box::new(BinaryTree {
+                    value: internal,
+                    left: None,
+                    right: None,
+                })
+            });
+        }
+
+        // nor end in an internal node
+        if tree.value.symbol.is_some() || tree.left.is_some() || tree.right.is_some() {
+            return None;
+        }
+        tree.value.symbol = Some(symbol);
+    }
+
+    Some(root)
+}
+
+/// Optimal code lengths with no code longer than `max_len`, computed by the package-merge
+/// algorithm of Larmore and Hirschberg. Symbols of zero frequency get a length of 0.
+///
+/// Think of every symbol as a coin of its frequency, present in every denomination 2^-1 up to
+/// 2^-max_len. Starting from the smallest denomination, the coins are paired ("packaged") into
+/// coins of the next denomination, and merged with the symbol coins of that denomination. The
+/// cheapest `2 * (n - 1)` items of the 2^-1 denomination have the minimal total frequency
+/// among all choices which sum up to `n - 1`, and the code length of a symbol is the number
+/// of its coins among them.
+///
+/// Packages list their symbols, so it takes O(max_len * n^2) for n symbols, which is fine for
+/// alphabets of bytes.
+///
+/// Panics if `max_len` is too short to give every symbol a distinct codeword.
+pub fn length_limited_code_lengths(frequencies: &[usize], max_len: u32) -> Vec<u32> {
+    let mut lengths = vec![0; frequencies.len()];
+
+    let mut leaves: Vec<(usize, Vec<usize>)> = frequencies
+        .iter()
+        .enumerate()
+        .filter(|(_, frequency)| **frequency > 0)
+        .map(|(symbol, frequency)| (*frequency, vec![symbol]))
+        .collect();
+    // stable sort, so that equal frequencies keep the order of symbol ids
+    leaves.sort_by_key(|(frequency, _)| *frequency);
+
+    let n = leaves.len();
+    if n == 0 {
+        return lengths;
+    }
+    if n == 1 {
+        lengths[leaves[0].1[0]] = 1;
+        return lengths;
+    }
+    assert!(
+        max_len < usize::BITS && n <= 1 << max_len,
+        "{} symbols don't fit into codes of at most {} bits",
+        n,
+        max_len
+    );
+
+    let mut items = leaves.clone();
+    for _ in 1..max_len {
+        let packages: Vec<(usize, Vec<usize>)> = items
+            .chunks_exact(2)
+            .map(|pair| {
+                let mut symbols = pair[0].1.clone();
+                symbols.extend_from_slice(&pair[1].1);
+                (pair[0].0 + pair[1].0, symbols)
+            })
+            .collect();
+
+        // merge both sorted lists, leaves go first among equal weights
+        items = Vec::with_capacity(leaves.len() + packages.len());
+        let (mut i, mut j) = (0, 0);
+        while i < leaves.len() || j < packages.len() {
+            if j == packages.len() || (i < leaves.len() && leaves[i].0 <= packages[j].0) {
+                items.push(leaves[i].clone());
+                i += 1;
+            } else {
+                items.push(packages[j].clone());
+                j += 1;
+            }
+        }
+    }
+
+    for (_, symbols) in items.iter().take(2 * (n - 1)) {
+        for symbol in symbols {
+            lengths[*symbol] += 1;
+        }
+    }
+
+    lengths
+}
+
+/// Compresses `data` with a Huffman code of its bytes.
+///
+/// Layout of the output, packed by `BitWriter`:
+/// - number of bytes of `data`, 64 bits
+/// - the code tree in pre-order (omitted for empty `data`): bit 0 for an internal node followed
+///   by its left and right subtrees, bit 1 for a leaf followed by its 8 bits of the byte value
+/// - the codewords of the bytes of `data`
+pub fn encode(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::with_capacity(data.len() / 2 + 8);
+    writer.write_bits(data.len() as u64, 64);
+
+    if data.is_empty() {
+        return writer.finish();
+    }
+
+    let code_tree = compute_code_tree(&byte_frequencies(data));
+    write_code_tree(&code_tree, &mut writer);
+
+    let codes = code_table(&code_tree);
+    for byte in data {
+        let code = codes[*byte as usize].unwrap();
+        writer.write_bits(code.bits, code.len);
+    }
+
+    writer.finish()
+}
+
+/// Longest data restored from the code of a single symbol. Its codewords take no bits, so
+/// nothing else bounds the length read from corrupted data.
+pub const MAX_DECODED_LEN: usize = 1 << 30;
+
+/// Restores the data compressed by `encode`.
+pub fn decode(encoded: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let mut reader = BitReader::new(encoded);
+    let len = reader.read_bits(64).ok_or(DecodeError::UnexpectedEnd)? as usize;
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+
+    let code_tree = read_code_tree(&mut reader, 0)?;
+    if let Some(symbol) = code_tree.value.symbol {
+        if len > MAX_DECODED_LEN {
+            return Err(DecodeError::TooLong);
+        }
+        return Ok(vec![symbol as u8; len]);
+    }
+
+    // every codeword takes a bit at least, don't trust the length of corrupted data
+    if len > reader.remaining() {
+        return Err(DecodeError::UnexpectedEnd);
     }
+    let mut data = Vec::with_capacity(len);
+    while data.len() < len {
+        let mut tree = &code_tree;
+        while tree.value.symbol.is_none() {
+            let bit = reader.read_bit().ok_or(DecodeError::UnexpectedEnd)?;
+            let next = if bit { &tree.right } else { &tree.left };
+            tree = next.as_deref().ok_or(DecodeError::InvalidCodeTree)?;
+        }
+        data.push(tree.value.symbol.unwrap() as u8);
+    }
+
+    Ok(data)
+}
+
+/// Huffman code of the frequencies of a `FrequencyModel`, the same tree on both sides so none
+/// is stored in the output.
+pub struct HuffmanCoder;
+
+impl EntropyCoder for HuffmanCoder {
+    fn name(&self) -> &'static str {
+        "huffman"
+    }
+
+    fn encode(&self, model: &FrequencyModel, data: &[u8]) -> Vec<u8> {
+        let mut writer = BitWriter::with_capacity(data.len() / 2);
+        if data.is_empty() {
+            return writer.finish();
+        }
+
+        let codes = code_table(&model_code_tree(model));
+        for byte in data {
+            let code = codes
+                .get(*byte as usize)
+                .copied()
+                .flatten()
+                .unwrap_or_else(|| panic!("Byte {} isn't in the model", byte));
+            writer.write_bits(code.bits, code.len);
+        }
+
+        writer.finish()
+    }
+
+    fn decode(
+        &self,
+        model: &FrequencyModel,
+        encoded: &[u8],
+        len: usize,
+    ) -> Result<Vec<u8>, DecodeError> {
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+
+        let code_tree = model_code_tree(model);
+        if let Some(symbol) = code_tree.value.symbol {
+            if len > MAX_DECODED_LEN {
+                return Err(DecodeError::TooLong);
+            }
+            return Ok(vec![symbol as u8; len]);
+        }
+
+        let mut reader = BitReader::new(encoded);
+        // every codeword takes a bit at least
+        if len > reader.remaining() {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+        let mut data = Vec::with_capacity(len);
+        while data.len() < len {
+            let mut tree = &code_tree;
+            while tree.value.symbol.is_none() {
+                let bit = reader.read_bit().ok_or(DecodeError::UnexpectedEnd)?;
+                let next = if bit { &tree.right } else { &tree.left };
+                tree = next.as_deref().unwrap();
+            }
+            data.push(tree.value.symbol.unwrap() as u8);
+        }
+
+        Ok(data)
+    }
+}
+
+fn model_code_tree(model: &FrequencyModel) -> BinaryTree<CodeNode> {
+    let frequencies: Vec<usize> = model
+        .frequencies()
+        .iter()
+        .map(|frequency| *frequency as usize)
+        .collect();
+    compute_code_tree(&frequencies)
+}
+
+fn write_code_tree(tree: &BinaryTree<CodeNode>, writer: &mut BitWriter) {
+    match tree.value.symbol {
+        Some(symbol) => {
+            writer.write_bit(true);
+            writer.write_bits(symbol as u64, 8);
+        }
+        None => {
+            writer.write_bit(false);
+            write_code_tree(tree.left.as_ref().unwrap(), writer);
+            write_code_tree(tree.right.as_ref().unwrap(), writer);
+        }
+    }
+}
+
+// a tree of 256 leaves is at most 255 levels deep, anything deeper is corrupted
+pub(crate) fn read_code_tree(
+    reader: &mut BitReader,
+    depth: usize,
+) -> Result<BinaryTree<CodeNode>, DecodeError> {
+    if depth > 255 {
+        return Err(DecodeError::InvalidCodeTree);
+    }
+
+    let is_leaf = reader.read_bit().ok_or(DecodeError::UnexpectedEnd)?;
+    if is_leaf {
+        let symbol = reader.read_bits(8).ok_or(DecodeError::UnexpectedEnd)? as usize;
+        return Ok(BinaryTree {
+            value: CodeNode {
+                weight: 0,
+                symbol: Some(symbol),
+            },
+            left: None,
+            right: None,
+        });
+    }
+
+    let left = read_code_tree(reader, depth + 1)?;
+    let right = read_code_tree(reader, depth + 1)?;
+    Ok(BinaryTree {
+        value: CodeNode {
+            weight: 0,
+            symbol: None,
+        },
+        left: Some(Box::new(left)),
+        right: Some(Box::new(right)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+
+    #[test]
+    fn test_code_table() {
+        // "a" * 5, "b" * 2, "c" * 1, "d" * 1
+        let code_tree = compute_code_tree(&[5, 2, 1, 1]);
+        assert_eq!(code_tree.value.weight, 9);
+        let codes = code_table(&code_tree);
+        let lens: Vec<u32> = codes.iter().map(|code| code.unwrap().len).collect();
+        assert_eq!(lens, vec![1, 2, 3, 3]);
+
+        let codes = code_table(&compute_code_tree(&[0, 0, 7]));
+        assert_eq!(codes, vec![None, None, Some(Codeword { bits: 0, len: 0 })]);
+    }
+
+    #[test]
+    fn test_compute_code_tree_duplicate_frequencies() {
+        // every leaf survives even though all the weights are the same
+        let code_tree = compute_code_tree(&[3, 3, 3, 3]);
+        assert_eq!(code_tree.value.weight, 12);
+        let codes = code_table(&code_tree);
+        assert_eq!(
+            codes,
+            vec![
+                Some(Codeword { bits: 0b00, len: 2 }),
+                Some(Codeword { bits: 0b01, len: 2 }),
+                Some(Codeword { bits: 0b10, len: 2 }),
+                Some(Codeword { bits: 0b11, len: 2 }),
+            ]
+        );
 
-    // return unique tree remaining in the forest
-    let mut code_tree: Vec<BinaryTree<usize>> = forest.into_values().collect();
-    code_tree.remove(0)
+        // ties between a merged tree and a leaf go to the leaf pushed earlier
+        let codes = code_table(&compute_code_tree(&[1, 1, 2, 2, 4]));
+        let lens: Vec<u32> = codes.iter().map(|code| code.unwrap().len).collect();
+        assert_eq!(lens, vec![3, 3, 2, 2, 2]);
+
+        let mut rng = thread_rng();
+        let frequencies: Vec<usize> = (0..500).map(|_| rng.gen_range(1..10)).collect();
+        let codes = code_table(&compute_code_tree(&frequencies));
+        assert_eq!(codes.len(), frequencies.len());
+        assert!(codes.iter().all(|code| code.is_some()));
+        for _ in 0..5 {
+            assert_eq!(code_table(&compute_code_tree(&frequencies)), codes);
+        }
+    }
+
+    #[test]
+    fn test_dary_code_tree() {
+        fn code_lens(frequencies: &[usize], arity: usize) -> Vec<usize> {
+            dary_code_table(&compute_dary_code_tree(frequencies, arity))
+                .iter()
+                .map(|code| code.as_ref().map_or(0, |code| code.len()))
+                .collect()
+        }
+
+        // arity 2 gives the same lengths as the binary tree
+        let frequencies = [5, 2, 1, 1, 7, 3, 0, 12];
+        let binary: Vec<usize> = code_table(&compute_code_tree(&frequencies))
+            .iter()
+            .map(|code| code.map_or(0, |code| code.len as usize))
+            .collect();
+        assert_eq!(code_lens(&frequencies, 2), binary);
+
+        // 6 symbols take a dummy to fill a ternary tree, merging three real symbols first would
+        // leave the root with only two children and cost 12 digits instead of 11
+        let lens = code_lens(&[1; 6], 3);
+        assert_eq!(lens.iter().sum::<usize>(), 11);
+        assert_eq!(lens.iter().filter(|len| **len == 1).count(), 1);
+
+        let code_tree = compute_dary_code_tree(&[1; 7], 3);
+        assert_eq!(code_tree.children.len(), 3);
+        assert_eq!(code_lens(&[1; 7], 3).iter().sum::<usize>(), 13);
+        assert_eq!(code_lens(&[0, 4], 5), vec![0, 0]);
+
+        // an optimal code fills the tree, Kraft sum of 1 but for the dummies
+        let mut rng = thread_rng();
+        for arity in 2..8 {
+            let frequencies: Vec<usize> = (0..100).map(|_| rng.gen_range(1..1000)).collect();
+            let lens = code_lens(&frequencies, arity);
+            let dummies = (arity - 1 - (frequencies.len() - 1) % (arity - 1)) % (arity - 1);
+            let max_len = *lens.iter().max().unwrap() as u32;
+            let kraft: usize = lens
+                .iter()
+                .map(|len| arity.pow(max_len - *len as u32))
+                .sum();
+            assert_eq!(kraft + dummies, arity.pow(max_len));
+        }
+    }
+
+    #[test]
+    fn test_dary_decode() {
+        let data = b"this is an example of a huffman tree";
+        let frequencies = byte_frequencies(data);
+        for arity in [2, 3, 4, 10, 256] {
+            let code_tree = compute_dary_code_tree(&frequencies, arity);
+            let codes = dary_code_table(&code_tree);
+            let digits: Vec<u8> = data
+                .iter()
+                .flat_map(|byte| codes[*byte as usize].clone().unwrap())
+                .collect();
+            assert!(digits.iter().all(|digit| (*digit as usize) < arity));
+            let decoded = dary_decode(&code_tree, &digits, data.len()).unwrap();
+            assert!(decoded
+                .iter()
+                .map(|symbol| *symbol as u8)
+                .eq(data.iter().copied()));
+            assert_eq!(
+                dary_decode(&code_tree, &digits[..digits.len() - 1], data.len()),
+                Err(DecodeError::UnexpectedEnd)
+            );
+            assert_eq!(
+                dary_decode(&code_tree, &digits, usize::MAX),
+                Err(DecodeError::UnexpectedEnd)
+            );
+        }
+
+        let single_leaf = compute_dary_code_tree(&byte_frequencies(b"aaa"), 3);
+        assert_eq!(
+            dary_decode(&single_leaf, &[], 3).unwrap(),
+            [b'a' as usize; 3]
+        );
+        assert_eq!(
+            dary_decode(&single_leaf, &[], usize::MAX),
+            Err(DecodeError::TooLong)
+        );
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let text = b"this is an example of a huffman tree";
+        let encoded = encode(text);
+        // 8 bytes of length + tree of 16 leaves (16 * 9 + 15 bits) + 135 bits of data
+        assert_eq!(encoded.len(), 8 + (16 * 9 + 15 + 135_usize).div_ceil(8));
+        assert_eq!(decode(&encoded).unwrap(), text);
+
+        for data in [&b""[..], b"a", b"aaaaaaaa", b"ab", &[0, 255, 0, 255, 128]] {
+            assert_eq!(decode(&encode(data)).unwrap(), data);
+        }
+
+        let mut rng = thread_rng();
+        let data: Vec<u8> = (0..10_000).map(|_| rng.gen_range(0..=255)).collect();
+        assert_eq!(decode(&encode(&data)).unwrap(), data);
+        // skewed distribution compresses
+        let data: Vec<u8> = (0..10_000)
+            .map(|_| rng.gen_range(0..8) * rng.gen_range(0..2))
+            .collect();
+        let encoded = encode(&data);
+        assert!(encoded.len() < data.len() / 3);
+        assert_eq!(decode(&encoded).unwrap(), data);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(decode(&[]), Err(DecodeError::UnexpectedEnd));
+        let encoded = encode(b"abracadabra");
+        assert_eq!(
+            decode(&encoded[..encoded.len() - 1]),
+            Err(DecodeError::UnexpectedEnd)
+        );
+        // length of 1 and a tree which only goes left
+        let mut corrupted = vec![0, 0, 0, 0, 0, 0, 0, 1];
+        corrupted.extend_from_slice(&[0; 40]);
+        assert_eq!(decode(&corrupted), Err(DecodeError::InvalidCodeTree));
+
+        // a huge length and a single leaf
+        let huge = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xb0, 0x80];
+        assert_eq!(decode(&huge), Err(DecodeError::TooLong));
+        // a huge length and two leaves
+        let mut huge = vec![0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+        huge.extend_from_slice(&encode(b"ab")[8..]);
+        assert_eq!(decode(&huge), Err(DecodeError::UnexpectedEnd));
+
+        let coder = HuffmanCoder;
+        let model = FrequencyModel::from_data(b"aaaa");
+        assert_eq!(
+            coder.decode(&model, &[], usize::MAX),
+            Err(DecodeError::TooLong)
+        );
+        let model = FrequencyModel::from_data(b"abab");
+        assert_eq!(
+            coder.decode(&model, &[0xff], usize::MAX),
+            Err(DecodeError::UnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn test_canonical_code_table() {
+        // example of RFC 1951, symbols A..H
+        let codes = canonical_code_table(&[3, 3, 3, 3, 3, 2, 4, 4]);
+        let expected = [
+            (0b010, 3),
+            (0b011, 3),
+            (0b100, 3),
+            (0b101, 3),
+            (0b110, 3),
+            (0b00, 2),
+            (0b1110, 4),
+            (0b1111, 4),
+        ];
+        for (code, (bits, len)) in codes.iter().zip(expected) {
+            assert_eq!(*code, Some(Codeword { bits, len }));
+        }
+
+        let codes = canonical_code_table(&[0, 1, 0, 1]);
+        assert_eq!(
+            codes,
+            vec![
+                None,
+                Some(Codeword { bits: 0, len: 1 }),
+                None,
+                Some(Codeword { bits: 1, len: 1 })
+            ]
+        );
+        assert!(canonical_code_table(&[]).is_empty());
+
+        // canonical codes of Huffman lengths are a prefix code with the same lengths
+        let frequencies = byte_frequencies(b"this is an example of a huffman tree");
+        let lengths = code_lengths(&compute_code_tree(&frequencies));
+        let codes: Vec<Codeword> = canonical_code_table(&lengths)
+            .into_iter()
+            .flatten()
+            .collect();
+        for (i, a) in codes.iter().enumerate() {
+            for b in codes.iter().skip(i + 1) {
+                let len = cmp::min(a.len, b.len);
+                assert_ne!(a.bits >> (a.len - len), b.bits >> (b.len - len));
+            }
+        }
+    }
+
+    #[test]
+    fn test_code_tree_from_codes() {
+        let frequencies = byte_frequencies(b"abracadabra");
+        let codes = code_table(&compute_code_tree(&frequencies));
+        let code_tree = code_tree_from_codes(&codes).unwrap();
+        assert_eq!(code_table(&code_tree), codes);
+
+        let codes = canonical_code_table(&[2, 0, 2, 1]);
+        assert_eq!(code_table(&code_tree_from_codes(&codes).unwrap()), codes);
+
+        let prefix = vec![
+            Some(Codeword { bits: 0b1, len: 1 }),
+            Some(Codeword { bits: 0b10, len: 2 }),
+        ];
+        assert!(code_tree_from_codes(&prefix).is_none());
+        let prefix = vec![
+            Some(Codeword { bits: 0b10, len: 2 }),
+            Some(Codeword { bits: 0b1, len: 1 }),
+        ];
+        assert!(code_tree_from_codes(&prefix).is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_canonical_code_table_panic_kraft() {
+        canonical_code_table(&[1, 1, 1]);
+    }
+
+    // minimal cost over all the code lengths of at most "max_len" bits which satisfy
+    // the Kraft inequality
+    fn brute_force_cost(frequencies: &[usize], max_len: u32) -> usize {
+        let n = frequencies.len();
+        let mut best = usize::MAX;
+        let mut lengths = vec![1u32; n];
+        loop {
+            let kraft: f64 = lengths.iter().map(|len| 0.5f64.powi(*len as i32)).sum();
+            if kraft <= 1.0 {
+                let cost = lengths
+                    .iter()
+                    .zip(frequencies)
+                    .map(|(len, frequency)| *len as usize * frequency)
+                    .sum();
+                best = cmp::min(best, cost);
+            }
+
+            let mut i = 0;
+            while i < n && lengths[i] == max_len {
+                lengths[i] = 1;
+                i += 1;
+            }
+            if i == n {
+                return best;
+            }
+            lengths[i] += 1;
+        }
+    }
+
+    #[test]
+    fn test_length_limited_code_lengths() {
+        let frequencies = [1, 1, 2, 4, 8, 16];
+        assert_eq!(
+            length_limited_code_lengths(&frequencies, 10),
+            code_lengths(&compute_code_tree(&frequencies))
+        );
+        assert_eq!(
+            length_limited_code_lengths(&frequencies, 3),
+            vec![3, 3, 3, 3, 2, 2]
+        );
+        assert_eq!(length_limited_code_lengths(&[0, 5, 0], 1), vec![0, 1, 0]);
+        assert_eq!(length_limited_code_lengths(&[0, 0], 4), vec![0, 0]);
+
+        let mut rng = thread_rng();
+        for _ in 0..50 {
+            let n = rng.gen_range(2..=6);
+            let frequencies: Vec<usize> = (0..n).map(|_| rng.gen_range(1..100)).collect();
+            let min_len = (n as f64).log2().ceil() as u32;
+            for max_len in min_len..=n as u32 {
+                let lengths = length_limited_code_lengths(&frequencies, max_len);
+                assert!(lengths.iter().all(|len| *len >= 1 && *len <= max_len));
+                let cost: usize = lengths
+                    .iter()
+                    .zip(&frequencies)
+                    .map(|(l, f)| *l as usize * f)
+                    .sum();
+                assert_eq!(
+                    cost,
+                    brute_force_cost(&frequencies, max_len),
+                    "frequencies={:?}, max_len={}",
+                    frequencies,
+                    max_len
+                );
+                // the lengths always give a valid canonical code
+                assert_eq!(canonical_code_table(&lengths).len(), n);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_length_limited_code_lengths_panic_too_short() {
+        length_limited_code_lengths(&[1, 1, 1, 1, 1], 2);
+    }
+
+    #[test]
+    fn test_traversals() {
+        //       1
+        //      / \
+        //     2   3
+        //    / \   \
+        //   4   5   6
+        //          /
+        //         7
+        let tree = BinaryTree {
+            value: 1,
+            left: Some(Box::new(BinaryTree {
+                value: 2,
+                left: Some(Box::new(BinaryTree::leaf(4))),
+                right: Some(Box::new(BinaryTree::leaf(5))),
+            })),
+            right: Some(Box::new(BinaryTree {
+                value: 3,
+                left: None,
+                right: Some(Box::new(BinaryTree {
+                    value: 6,
+                    left: Some(Box::new(BinaryTree::leaf(7))),
+                    right: None,
+                })),
+            })),
+        };
+
+        assert_eq!(
+            tree.pre_order().copied().collect::<Vec<_>>(),
+            [1, 2, 4, 5, 3, 6, 7]
+        );
+        assert_eq!(
+            tree.in_order().copied().collect::<Vec<_>>(),
+            [4, 2, 5, 1, 3, 7, 6]
+        );
+        assert_eq!(
+            tree.post_order().copied().collect::<Vec<_>>(),
+            [4, 5, 2, 7, 6, 3, 1]
+        );
+        assert_eq!(
+            tree.level_order().copied().collect::<Vec<_>>(),
+            [1, 2, 3, 4, 5, 6, 7]
+        );
+        assert_eq!(tree.height(), 3);
+        assert_eq!(tree.size(), 7);
+        assert_eq!(tree.leaves().copied().collect::<Vec<_>>(), [4, 5, 7]);
+        assert_eq!(
+            tree.paths_to_leaves(),
+            [
+                (vec![false, false], &4),
+                (vec![false, true], &5),
+                (vec![true, true, false], &7)
+            ]
+        );
+
+        // works for values which aren't even comparable
+        let labelled = tree.map(|value| format!("#{}", value));
+        assert_eq!(labelled.in_order().nth(3).unwrap(), "#1");
+        let single = BinaryTree::leaf(0.5);
+        assert_eq!((single.height(), single.size()), (0, 1));
+        assert_eq!(single.paths_to_leaves(), [(vec![], &0.5)]);
+    }
+
+    #[test]
+    fn test_paths_to_leaves_match_code_table() {
+        let code_tree = compute_code_tree(&[5, 2, 1, 1, 7, 3]);
+        let codes = code_table(&code_tree);
+        for (path, node) in code_tree.paths_to_leaves() {
+            let code = codes[node.symbol.unwrap()].unwrap();
+            let bits = path.iter().fold(0, |bits, bit| (bits << 1) | *bit as u64);
+            assert_eq!((bits, path.len() as u32), (code.bits, code.len));
+        }
+        assert_eq!(code_tree.height(), code_tree.stats().max_codeword_len);
+        assert_eq!(code_tree.size(), 2 * 6 - 1);
+    }
+
+    #[test]
+    fn test_render_code_tree() {
+        let code_tree = compute_code_tree(&[5, 2, 2]);
+        assert_eq!(
+            code_tree.to_ascii(),
+            "9\n\
+             ├─0─ 4\n\
+             │   ├─0─ 1: 2\n\
+             │   └─1─ 2: 2\n\
+             └─1─ 0: 5\n"
+        );
+        assert_eq!(
+            code_tree.to_dot(),
+            "digraph {\n    \
+             n0 [label=\"9\", shape=ellipse];\n    \
+             n1 [label=\"4\", shape=ellipse];\n    \
+             n2 [label=\"1: 2\", shape=box];\n    \
+             n1 -> n2 [label=\"0\"];\n    \
+             n3 [label=\"2: 2\", shape=box];\n    \
+             n1 -> n3 [label=\"1\"];\n    \
+             n0 -> n1 [label=\"0\"];\n    \
+             n4 [label=\"0: 5\", shape=box];\n    \
+             n0 -> n4 [label=\"1\"];\n\
+             }\n"
+        );
+
+        let mut frequencies = vec![0; 256];
+        frequencies[b'"' as usize] = 1;
+        frequencies[b'\n' as usize] = 1;
+        let code_tree = compute_code_tree(&frequencies);
+        assert_eq!(
+            code_tree.to_ascii_with(byte_node_label),
+            "2\n├─0─ 0x0a: 1\n└─1─ '\"': 1\n"
+        );
+        assert!(code_tree
+            .to_dot_with(byte_node_label)
+            .contains("[label=\"'\\\"': 1\", shape=box]"));
+    }
+
+    #[test]
+    fn test_code_tree_stats() {
+        let stats = compute_code_tree(&[5, 2, 1, 1]).stats();
+        assert_eq!(stats.symbols, 4);
+        assert_eq!(stats.min_codeword_len, 1);
+        assert_eq!(stats.max_codeword_len, 3);
+        assert_eq!(stats.average_codeword_len, 9.0 / 4.0);
+        assert_eq!(stats.weighted_average_len, 15.0 / 9.0);
+        assert!((stats.entropy - 1.6577).abs() < 1e-4);
+        assert!(stats.efficiency > 0.99 && stats.efficiency <= 1.0);
+
+        // dyadic frequencies are coded without any loss
+        let stats = compute_code_tree(&[8, 4, 2, 1, 1]).stats();
+        assert_eq!(stats.weighted_average_len, stats.entropy);
+        assert_eq!(stats.efficiency, 1.0);
+
+        let stats = compute_code_tree(&[0, 3]).stats();
+        assert_eq!(stats.symbols, 1);
+        assert_eq!(stats.max_codeword_len, 0);
+        assert_eq!(stats.entropy, 0.0);
+        assert_eq!(stats.efficiency, 1.0);
+    }
 }
@@ -0,0 +1,343 @@
+use crate::bitstream::BitWriter;
+use crate::crc32::Crc32;
+use crate::huffman::{self, BinaryTree, CodeNode, Codeword};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+pub const MAGIC: [u8; 4] = *b"HUFC";
+pub const VERSION: u8 = 1;
+/// Longest codeword of the container, short enough for fast table decoding.
+pub const MAX_CODE_LEN: u32 = 15;
+
+const HEADER_LEN: usize = 4 + 1 + 8 + 4 + 256;
+const CHUNK_LEN: usize = 64 * 1024;
+
+#[derive(Debug)]
+pub enum ContainerError {
+    Io(io::Error),
+    /// The input doesn't start with `MAGIC`.
+    BadMagic,
+    UnsupportedVersion(u8),
+    /// The input ended within the header.
+    HeaderTooShort,
+    /// The code lengths of the header don't form a prefix code.
+    InvalidCodeTable,
+    /// The compressed data contains a bit sequence which is not a codeword.
+    InvalidCodeword,
+    /// The input ended before all the bytes were decompressed.
+    Truncated {
+        expected: u64,
+        decompressed: u64,
+    },
+    ChecksumMismatch {
+        expected: u32,
+        actual: u32,
+    },
+}
+
+impl fmt::Display for ContainerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ContainerError::Io(err) => write!(f, "I/O error: {}", err),
+            ContainerError::BadMagic => write!(f, "not a compressed file (bad magic number)"),
+            ContainerError::UnsupportedVersion(version) => {
+                write!(f, "unsupported container version {}", version)
+            }
+            ContainerError::HeaderTooShort => write!(
+                f,
+                "input too short for a container header of {} bytes",
+                HEADER_LEN
+            ),
+            ContainerError::InvalidCodeTable => write!(f, "corrupted code table in the header"),
+            ContainerError::InvalidCodeword => write!(f, "corrupted compressed data"),
+            ContainerError::Truncated {
+                expected,
+                decompressed,
+            } => write!(
+                f,
+                "truncated input: decompressed {} of {} bytes",
+                decompressed, expected
+            ),
+            ContainerError::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "checksum mismatch: expected {:08x}, got {:08x}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl Error for ContainerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ContainerError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ContainerError {
+    fn from(err: io::Error) -> Self {
+        ContainerError::Io(err)
+    }
+}
+
+/// Compresses `input` into `output` with a canonical Huffman code of its bytes.
+///
+/// The input is read twice, first to count the byte frequencies and compute the checksum, then
+/// to encode it, so it is never held in memory as a whole.
+///
+/// Layout of the container:
+/// - `MAGIC`, 4 bytes
+/// - `VERSION`, 1 byte
+/// - length of the original data, 8 bytes little endian
+/// - CRC-32 of the original data, 4 bytes little endian
+/// - code length of every byte value, 256 bytes, 0 for bytes which don't occur
+/// - the codewords of the canonical code of these lengths, packed by `BitWriter`
+///
+/// Returns the number of bytes written.
+pub fn compress<R: Read + Seek, W: Write>(input: &mut R, output: &mut W) -> io::Result<u64> {
+    let mut frequencies = vec![0; 256];
+    let mut crc = Crc32::new();
+    let mut original_len = 0u64;
+    let mut chunk = vec![0u8; CHUNK_LEN];
+    loop {
+        let read = input.read(&mut chunk)?;
+        if read == 0 {
+            break;
+        }
+        for byte in &chunk[..read] {
+            frequencies[*byte as usize] += 1;
+        }
+        crc.update(&chunk[..read]);
+        original_len += read as u64;
+    }
+
+    let lengths = huffman::length_limited_code_lengths(&frequencies, MAX_CODE_LEN);
+    let codes = huffman::canonical_code_table(&lengths);
+
+    output.write_all(&MAGIC)?;
+    output.write_all(&[VERSION])?;
+    output.write_all(&original_len.to_le_bytes())?;
+    output.write_all(&crc.finish().to_le_bytes())?;
+    let length_bytes: Vec<u8> = lengths.iter().map(|len| *len as u8).collect();
+    output.write_all(&length_bytes)?;
+    let mut written = HEADER_LEN as u64;
+
+    input.seek(SeekFrom::Start(0))?;
+    let mut writer = BitWriter::with_capacity(CHUNK_LEN);
+    loop {
+        let read = input.read(&mut chunk)?;
+        if read == 0 {
+            break;
+        }
+        for byte in &chunk[..read] {
+            let code = codes[*byte as usize].unwrap();
+            writer.write_bits(code.bits, code.len);
+        }
+        let bytes = writer.take_bytes();
+        output.write_all(&bytes)?;
+        written += bytes.len() as u64;
+    }
+    let bytes = writer.finish();
+    output.write_all(&bytes)?;
+    written += bytes.len() as u64;
+
+    Ok(written)
+}
+
+/// Decompresses a container written by `compress` into `output`, checking the length and the
+/// checksum of the result. Returns the number of bytes written.
+pub fn decompress<R: Read, W: Write>(input: &mut R, output: &mut W) -> Result<u64, ContainerError> {
+    let mut header = [0u8; HEADER_LEN];
+    read_header(input, &mut header)?;
+    if header[..4] != MAGIC {
+        return Err(ContainerError::BadMagic);
+    }
+    if header[4] != VERSION {
+        return Err(ContainerError::UnsupportedVersion(header[4]));
+    }
+    let original_len = u64::from_le_bytes(header[5..13].try_into().unwrap());
+    let expected_crc = u32::from_le_bytes(header[13..17].try_into().unwrap());
+    let lengths: Vec<u32> = header[17..].iter().map(|len| *len as u32).collect();
+
+    let code_tree = code_tree_from_lengths(&lengths).ok_or(ContainerError::InvalidCodeTable)?;
+    if original_len > 0 && code_tree.left.is_none() && code_tree.right.is_none() {
+        return Err(ContainerError::InvalidCodeTable);
+    }
+
+    let mut crc = Crc32::new();
+    let mut decompressed = 0u64;
+    let mut decoded = Vec::with_capacity(CHUNK_LEN);
+    let mut chunk = vec![0u8; CHUNK_LEN];
+    // the position in the tree is kept between chunks, as codewords can span them
+    let mut tree = &code_tree;
+    'chunks: while decompressed < original_len {
+        let read = input.read(&mut chunk)?;
+        if read == 0 {
+            break;
+        }
+        for byte in &chunk[..read] {
+            for shift in (0..8).rev() {
+                let next = if (byte >> shift) & 1 == 1 {
+                    &tree.right
+                } else {
+                    &tree.left
+                };
+                tree = next.as_deref().ok_or(ContainerError::InvalidCodeword)?;
+                if let Some(symbol) = tree.value.symbol {
+                    decoded.push(symbol as u8);
+                    decompressed += 1;
+                    tree = &code_tree;
+                    // the rest of the last byte is padding
+                    if decompressed == original_len {
+                        break;
+                    }
+                }
+            }
+
+            if decoded.len() >= CHUNK_LEN || decompressed == original_len {
+                crc.update(&decoded);
+                output.write_all(&decoded)?;
+                decoded.clear();
+                if decompressed == original_len {
+                    break 'chunks;
+                }
+            }
+        }
+    }
+
+    if decompressed < original_len {
+        return Err(ContainerError::Truncated {
+            expected: original_len,
+            decompressed,
+        });
+    }
+    let actual_crc = crc.finish();
+    if actual_crc != expected_crc {
+        return Err(ContainerError::ChecksumMismatch {
+            expected: expected_crc,
+            actual: actual_crc,
+        });
+    }
+
+    Ok(decompressed)
+}
+
+fn read_header<R: Read>(input: &mut R, header: &mut [u8]) -> Result<(), ContainerError> {
+    match input.read_exact(header) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+            Err(ContainerError::HeaderTooShort)
+        }
+        Err(err) => Err(ContainerError::Io(err)),
+    }
+}
+
+// validates the lengths before building the canonical code, which panics on lengths
+// of no prefix code
+fn code_tree_from_lengths(lengths: &[u32]) -> Option<BinaryTree<CodeNode>> {
+    if lengths.iter().any(|len| *len > MAX_CODE_LEN) {
+        return None;
+    }
+    let kraft_sum: u64 = lengths
+        .iter()
+        .filter(|len| **len > 0)
+        .map(|len| 1u64 << (MAX_CODE_LEN - len))
+        .sum();
+    if kraft_sum > 1 << MAX_CODE_LEN {
+        return None;
+    }
+
+    let codes: Vec<Option<Codeword>> = huffman::canonical_code_table(lengths);
+    huffman::code_tree_from_codes(&codes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use std::io::Cursor;
+
+    fn compress_bytes(data: &[u8]) -> Vec<u8> {
+        let mut compressed = Vec::new();
+        let written = compress(&mut Cursor::new(data), &mut compressed).unwrap();
+        assert_eq!(written, compressed.len() as u64);
+        compressed
+    }
+
+    fn decompress_bytes(compressed: &[u8]) -> Result<Vec<u8>, ContainerError> {
+        let mut decompressed = Vec::new();
+        decompress(&mut Cursor::new(compressed), &mut decompressed)?;
+        Ok(decompressed)
+    }
+
+    #[test]
+    fn test_compress_decompress() {
+        for data in [&b""[..], b"a", b"aaaaaaaaa", b"abracadabra"] {
+            let compressed = compress_bytes(data);
+            assert_eq!(decompress_bytes(&compressed).unwrap(), data);
+        }
+
+        let mut rng = thread_rng();
+        // spans several chunks and uses codes up to the length limit
+        let data: Vec<u8> = (0..3 * CHUNK_LEN)
+            .map(|_| (rng.gen_range(0u32..1 << 20) as f64).log2() as u8)
+            .collect();
+        let compressed = compress_bytes(&data);
+        assert!(compressed.len() < data.len() / 2);
+        assert_eq!(decompress_bytes(&compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn test_decompress_corrupted() {
+        let compressed = compress_bytes(b"this is an example of a huffman tree");
+
+        let mut corrupted = compressed.clone();
+        corrupted[0] = b'X';
+        assert!(matches!(
+            decompress_bytes(&corrupted),
+            Err(ContainerError::BadMagic)
+        ));
+
+        let mut corrupted = compressed.clone();
+        corrupted[4] = 7;
+        assert!(matches!(
+            decompress_bytes(&corrupted),
+            Err(ContainerError::UnsupportedVersion(7))
+        ));
+
+        let mut corrupted = compressed.clone();
+        corrupted[17 + b'a' as usize] = 1;
+        corrupted[17 + b'b' as usize] = 1;
+        assert!(matches!(
+            decompress_bytes(&corrupted),
+            Err(ContainerError::InvalidCodeTable)
+        ));
+
+        assert!(matches!(
+            decompress_bytes(&compressed[..compressed.len() - 2]),
+            Err(ContainerError::Truncated { .. })
+        ));
+        assert!(matches!(
+            decompress_bytes(&compressed[..10]),
+            Err(ContainerError::HeaderTooShort)
+        ));
+        assert!(matches!(
+            decompress_bytes(&[]),
+            Err(ContainerError::HeaderTooShort)
+        ));
+
+        // flipping a bit of the data decodes into different bytes
+        let mut corrupted = compressed.clone();
+        corrupted[HEADER_LEN + 1] ^= 0b0001_0000;
+        assert!(decompress_bytes(&corrupted).is_err());
+
+        let mut corrupted = compressed.clone();
+        corrupted[13] ^= 1;
+        let err = decompress_bytes(&corrupted).unwrap_err();
+        assert!(matches!(err, ContainerError::ChecksumMismatch { .. }));
+        assert!(err.to_string().starts_with("checksum mismatch"));
+    }
+}
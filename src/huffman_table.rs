@@ -0,0 +1,243 @@
+use crate::bitstream::BitReader;
+use crate::huffman::{self, BinaryTree, CodeNode, DecodeError};
+
+/// Bits resolved by a single table lookup in `decode`, a table of 4096 entries.
+pub const DEFAULT_TABLE_BITS: u32 = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Link {
+    Leaf(usize),
+    Node(usize),
+    Missing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Entry {
+    /// The prefix starts with the codeword of `symbol`, `len` bits long.
+    Symbol { symbol: usize, len: u32 },
+    /// The prefix is the beginning of longer codewords, which continue below the node.
+    Node(usize),
+    /// No codeword starts with the prefix.
+    Invalid,
+}
+
+/// Huffman decoder resolving up to `table_bits` bits with a single lookup.
+///
+/// The table is indexed by the next `table_bits` bits of the input. Codewords of at most
+/// `table_bits` bits fill every entry they are a prefix of, longer codewords fall back to
+/// walking the tree bit by bit from the node reached after the first `table_bits` bits. The
+/// tree is flattened into an array, so the walk doesn't chase boxes either.
+pub struct TableDecoder {
+    table_bits: u32,
+    table: Vec<Entry>,
+    // children of the internal nodes, the root being the first
+    nodes: Vec<[Link; 2]>,
+    root: Link,
+}
+
+impl TableDecoder {
+    pub fn new(code_tree: &BinaryTree<CodeNode>, table_bits: u32) -> Self {
+        assert!(
+            (1..=24).contains(&table_bits),
+            "Expected 1 to 24 table bits, got {}",
+            table_bits
+        );
+        let mut nodes = Vec::new();
+        let root = flatten(code_tree, &mut nodes);
+
+        let table = (0..1usize << table_bits)
+            .map(|prefix| {
+                let mut link = root;
+                for len in 0..=table_bits {
+                    match link {
+                        Link::Leaf(symbol) => return Entry::Symbol { symbol, len },
+                        Link::Missing => return Entry::Invalid,
+                        Link::Node(node) if len < table_bits => {
+                            let bit = (prefix >> (table_bits - len - 1)) & 1;
+                            link = nodes[node][bit];
+                        }
+                        Link::Node(node) => return Entry::Node(node),
+                    }
+                }
+                unreachable!()
+            })
+            .collect();
+
+        TableDecoder {
+            table_bits,
+            table,
+            nodes,
+            root,
+        }
+    }
+
+    /// Decodes the next symbol.
+    pub fn decode_symbol(&self, reader: &mut BitReader) -> Result<usize, DecodeError> {
+        match self.table[reader.peek_bits(self.table_bits) as usize] {
+            Entry::Symbol { symbol, len } => {
+                reader.skip_bits(len).ok_or(DecodeError::UnexpectedEnd)?;
+                Ok(symbol)
+            }
+            Entry::Node(mut node) => {
+                reader
+                    .skip_bits(self.table_bits)
+                    .ok_or(DecodeError::UnexpectedEnd)?;
+                loop {
+                    let bit = reader.read_bit().ok_or(DecodeError::UnexpectedEnd)?;
+                    match self.nodes[node][bit as usize] {
+                        Link::Leaf(symbol) => return Ok(symbol),
+                        Link::Node(next) => node = next,
+                        Link::Missing => return Err(DecodeError::InvalidCodeTree),
+                    }
+                }
+            }
+            // zeros past the end of the input may not match a codeword
+            Entry::Invalid if reader.remaining() < self.table_bits as usize => {
+                Err(DecodeError::UnexpectedEnd)
+            }
+            Entry::Invalid => Err(DecodeError::InvalidCodeTree),
+        }
+    }
+
+    /// Decodes `len` bytes, the symbols of the tree being byte values.
+    pub fn decode_bytes(&self, reader: &mut BitReader, len: usize) -> Result<Vec<u8>, DecodeError> {
+        if let Link::Leaf(symbol) = self.root {
+            if len > huffman::MAX_DECODED_LEN {
+                return Err(DecodeError::TooLong);
+            }
+            return Ok(vec![symbol as u8; len]);
+        }
+
+        // every codeword takes a bit at least, don't trust the length of corrupted data
+        if len > reader.remaining() {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+        let mut data = Vec::with_capacity(len);
+        while data.len() < len {
+            data.push(self.decode_symbol(reader)? as u8);
+        }
+        Ok(data)
+    }
+}
+
+// appends the internal nodes of the subtree in pre-order, returns the link to its root
+fn flatten(tree: &BinaryTree<CodeNode>, nodes: &mut Vec<[Link; 2]>) -> Link {
+    if let Some(symbol) = tree.value.symbol {
+        return Link::Leaf(symbol);
+    }
+    let node = nodes.len();
+    nodes.push([Link::Missing; 2]);
+    for (i, child) in [&tree.left, &tree.right].into_iter().enumerate() {
+        if let Some(child) = child {
+            nodes[node][i] = flatten(child, nodes);
+        }
+    }
+    Link::Node(node)
+}
+
+/// Restores the data compressed by `huffman::encode` like `huffman::decode`, with a
+/// `TableDecoder` of `DEFAULT_TABLE_BITS` instead of walking the tree.
+pub fn decode(encoded: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let mut reader = BitReader::new(encoded);
+    let len = reader.read_bits(64).ok_or(DecodeError::UnexpectedEnd)? as usize;
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+
+    let code_tree = huffman::read_code_tree(&mut reader, 0)?;
+    TableDecoder::new(&code_tree, DEFAULT_TABLE_BITS).decode_bytes(&mut reader, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitstream::BitWriter;
+    use rand::{thread_rng, Rng};
+
+    #[test]
+    fn test_table_decoder() {
+        // codewords up to 20 bits long
+        let frequencies: Vec<usize> = (0..21).map(|i| 1 << i).collect();
+        let code_tree = huffman::compute_code_tree(&frequencies);
+        let codes = huffman::code_table(&code_tree);
+        assert_eq!(code_tree.height(), 20);
+
+        let mut rng = thread_rng();
+        let symbols: Vec<usize> = (0..5000).map(|_| rng.gen_range(0..21)).collect();
+        let mut writer = BitWriter::new();
+        for symbol in &symbols {
+            let code = codes[*symbol].unwrap();
+            writer.write_bits(code.bits, code.len);
+        }
+        let encoded = writer.finish();
+
+        // tables shorter and longer than the codewords
+        for table_bits in [1, 4, 12, 16, 20] {
+            let decoder = TableDecoder::new(&code_tree, table_bits);
+            let mut reader = BitReader::new(&encoded);
+            for symbol in &symbols {
+                assert_eq!(decoder.decode_symbol(&mut reader), Ok(*symbol));
+            }
+            assert!(reader.remaining() < 8);
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        let mut rng = thread_rng();
+        let skewed: Vec<u8> = (0..100_000)
+            .map(|_| (rng.gen_range(0u32..1 << 24) as f64).log2() as u8)
+            .collect();
+        let uniform: Vec<u8> = (0..10_000).map(|_| rng.gen()).collect();
+        for data in [&b""[..], b"a", b"aaaa", b"abracadabra", &skewed, &uniform] {
+            let encoded = huffman::encode(data);
+            assert_eq!(decode(&encoded).unwrap(), data);
+            assert_eq!(
+                decode(&encoded[..encoded.len() - 1]),
+                huffman::decode(&encoded[..encoded.len() - 1])
+            );
+        }
+
+        let huge = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xb0, 0x80];
+        assert_eq!(decode(&huge), Err(DecodeError::TooLong));
+        let mut huge = vec![0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+        huge.extend_from_slice(&huffman::encode(b"ab")[8..]);
+        assert_eq!(decode(&huge), Err(DecodeError::UnexpectedEnd));
+    }
+
+    #[test]
+    fn test_decode_incomplete_tree() {
+        // 0 is "a", 10 is "b", 11 isn't a codeword
+        let code_tree = BinaryTree {
+            value: CodeNode {
+                weight: 0,
+                symbol: None,
+            },
+            left: Some(Box::new(BinaryTree::leaf(CodeNode {
+                weight: 0,
+                symbol: Some(0),
+            }))),
+            right: Some(Box::new(BinaryTree {
+                value: CodeNode {
+                    weight: 0,
+                    symbol: None,
+                },
+                left: Some(Box::new(BinaryTree::leaf(CodeNode {
+                    weight: 0,
+                    symbol: Some(1),
+                }))),
+                right: None,
+            })),
+        };
+        for table_bits in [1, 8] {
+            let decoder = TableDecoder::new(&code_tree, table_bits);
+            let mut reader = BitReader::new(&[0b0101_1000, 0, 0]);
+            assert_eq!(decoder.decode_symbol(&mut reader), Ok(0));
+            assert_eq!(decoder.decode_symbol(&mut reader), Ok(1));
+            assert_eq!(
+                decoder.decode_symbol(&mut reader),
+                Err(DecodeError::InvalidCodeTree)
+            );
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use crate::mwis::Weight;
+
+/// Interval of time `start..finish`, not including its finish, so an interval finishing at
+/// the start of another is compatible with it. An interval of length 0 is compatible with the
+/// intervals it doesn't lie strictly inside of.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval<W> {
+    pub id: usize,
+    pub start: u64,
+    pub finish: u64,
+    pub weight: W,
+}
+
+/// Maximum weight set of pairwise compatible intervals: their ids by increasing finish time,
+/// then start time, and their total weight.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule<W> {
+    pub ids: Vec<usize>,
+    pub total_weight: W,
+}
+
+/// Computes the maximum weight set of non overlapping intervals, the interval version of
+/// `mwis::compute_mwis`. Intervals of negative weight are never chosen.
+///
+/// The intervals are sorted by finish time and the last interval compatible with every one is
+/// found by binary search. The best weight of the first `j` intervals then either skips the
+/// interval `j` or takes it with the best weight up to its predecessor, in O(n log n) time.
+///
+/// Panics if an interval finishes before it starts.
+pub fn compute_schedule<W: Weight>(intervals: &[Interval<W>]) -> Schedule<W> {
+    assert!(
+        intervals
+            .iter()
+            .all(|interval| interval.start <= interval.finish),
+        "An interval finishes before it starts"
+    );
+    let mut sorted = intervals.to_vec();
+    // intervals of length 0 after the others finishing at their start, compatible with them
+    sorted.sort_by_key(|interval| (interval.finish, interval.start));
+
+    // predecessors[j] is the number of intervals before the interval j finishing by its start,
+    // which doesn't count an interval of length 0 itself
+    let predecessors: Vec<usize> = sorted
+        .iter()
+        .enumerate()
+        .map(|(j, interval)| sorted[..j].partition_point(|other| other.finish <= interval.start))
+        .collect();
+
+    // best[j] is the weight of the best schedule of the first j intervals
+    let mut best = vec![W::ZERO; sorted.len() + 1];
+    for (j, interval) in sorted.iter().enumerate() {
+        let with_interval = best[predecessors[j]] + interval.weight;
+        best[j + 1] = if with_interval > best[j] {
+            with_interval
+        } else {
+            best[j]
+        };
+    }
+
+    let mut ids = Vec::new();
+    let mut j = sorted.len();
+    while j >= 1 {
+        if best[j - 1] >= best[j] {
+            j -= 1;
+        } else {
+            ids.push(sorted[j - 1].id);
+            j = predecessors[j - 1];
+        }
+    }
+    ids.reverse();
+
+    Schedule {
+        ids,
+        total_weight: best[sorted.len()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+
+    fn interval(id: usize, start: u64, finish: u64, weight: i64) -> Interval<i64> {
+        Interval {
+            id,
+            start,
+            finish,
+            weight,
+        }
+    }
+
+    #[test]
+    fn test_compute_schedule() {
+        let intervals = [
+            interval(0, 1, 4, 2),
+            interval(1, 3, 5, 4),
+            interval(2, 0, 6, 4),
+            interval(3, 4, 7, 7),
+            interval(4, 3, 9, 2),
+            interval(5, 5, 9, 1),
+            interval(6, 6, 10, 11),
+            interval(7, 8, 11, 5),
+        ];
+        let schedule = compute_schedule(&intervals);
+        assert_eq!(schedule.total_weight, 15);
+        assert_eq!(schedule.ids, [1, 6]);
+
+        let schedule = compute_schedule(&[interval(0, 0, 1, -1), interval(1, 1, 2, 3)]);
+        assert_eq!(schedule.ids, [1]);
+        assert_eq!(compute_schedule::<i64>(&[]).total_weight, 0);
+
+        // intervals of length 0
+        let schedule = compute_schedule(&[interval(0, 1, 3, 2), interval(1, 5, 5, 3)]);
+        assert_eq!(schedule.ids, [0, 1]);
+        assert_eq!(schedule.total_weight, 5);
+        let schedule = compute_schedule(&[
+            interval(0, 5, 5, 1),
+            interval(1, 2, 5, 1),
+            interval(2, 5, 5, 1),
+            interval(3, 4, 6, 5),
+            interval(4, 5, 7, 1),
+        ]);
+        assert_eq!(schedule.ids, [3]);
+        assert_eq!(compute_schedule(&[interval(0, 4, 4, 1)]).ids, [0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "An interval finishes before it starts")]
+    fn test_compute_schedule_panic_reversed() {
+        compute_schedule(&[interval(0, 1, 3, 2), interval(1, 5, 4, 3)]);
+    }
+
+    #[test]
+    fn test_compute_schedule_brute_force() {
+        let mut rng = thread_rng();
+        for _ in 0..200 {
+            let intervals: Vec<Interval<i64>> = (0..rng.gen_range(0..12))
+                .map(|id| {
+                    let start = rng.gen_range(0..20);
+                    interval(
+                        id,
+                        start,
+                        start + rng.gen_range(0..8),
+                        rng.gen_range(-2..10),
+                    )
+                })
+                .collect();
+            let schedule = compute_schedule(&intervals);
+
+            let chosen: Vec<&Interval<i64>> =
+                schedule.ids.iter().map(|id| &intervals[*id]).collect();
+            assert!(chosen
+                .windows(2)
+                .all(|pair| pair[0].finish <= pair[1].start));
+            let weight: i64 = chosen.iter().map(|interval| interval.weight).sum();
+            assert_eq!(weight, schedule.total_weight);
+
+            let best = (0..1usize << intervals.len())
+                .filter_map(|subset| {
+                    let mut chosen: Vec<&Interval<i64>> = (0..intervals.len())
+                        .filter(|i| subset & (1 << i) != 0)
+                        .map(|i| &intervals[i])
+                        .collect();
+                    chosen.sort_by_key(|interval| (interval.start, interval.finish));
+                    chosen
+                        .windows(2)
+                        .all(|pair| pair[0].finish <= pair[1].start)
+                        .then(|| chosen.iter().map(|interval| interval.weight).sum::<i64>())
+                })
+                .max()
+                .unwrap();
+            assert_eq!(schedule.total_weight, best);
+        }
+    }
+}
@@ -0,0 +1,417 @@
+use std::cmp;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Item {
+    pub value: u64,
+    pub weight: u64,
+}
+
+/// Items packed into a knapsack: their total value and weight, and every packed item index
+/// with the number of its copies, by increasing index.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Packing {
+    pub value: u64,
+    pub weight: u64,
+    pub items: Vec<(usize, u64)>,
+}
+
+impl Packing {
+    fn from_counts(items: &[Item], counts: &[u64]) -> Self {
+        let mut packing = Packing::default();
+        for (i, count) in counts.iter().enumerate() {
+            if *count > 0 {
+                packing.value += items[i].value * count;
+                packing.weight += items[i].weight * count;
+                packing.items.push((i, *count));
+            }
+        }
+        packing
+    }
+}
+
+/// Solves the 0/1 knapsack problem, every item packed at most once, with the O(n * capacity)
+/// dynamic program.
+///
+/// Only a row of values is kept, the items are reconstructed from a bit per item and capacity
+/// telling whether taking the item improved the value. See `knapsack_01_sparse` for capacities
+/// too big for that.
+pub fn knapsack_01(items: &[Item], capacity: u64) -> Packing {
+    let capacity = capacity as usize;
+    let mut values = vec![0u64; capacity + 1];
+    let mut taken = vec![false; items.len() * (capacity + 1)];
+    for (i, item) in items.iter().enumerate() {
+        let weight = item.weight as usize;
+        if weight > capacity {
+            continue;
+        }
+        // from the biggest capacity down, so values[c - weight] doesn't include the item yet
+        for c in (weight..=capacity).rev() {
+            let with_item = values[c - weight] + item.value;
+            if with_item > values[c] {
+                values[c] = with_item;
+                taken[i * (capacity + 1) + c] = true;
+            }
+        }
+    }
+
+    let mut counts = vec![0; items.len()];
+    let mut c = capacity;
+    for i in (0..items.len()).rev() {
+        if taken[i * (capacity + 1) + c] {
+            counts[i] = 1;
+            c -= items[i].weight as usize;
+        }
+    }
+    Packing::from_counts(items, &counts)
+}
+
+/// Solves the unbounded knapsack problem, every item packed any number of times, with the
+/// O(n * capacity) dynamic program. The last item added at every capacity is kept for the
+/// reconstruction.
+///
+/// Panics if an item weighs nothing, as its copies would be worth an unbounded value.
+pub fn knapsack_unbounded(items: &[Item], capacity: u64) -> Packing {
+    assert!(
+        items.iter().all(|item| item.weight > 0),
+        "Items of the unbounded knapsack should weigh more than 0"
+    );
+    let capacity = capacity as usize;
+    let mut values = vec![0u64; capacity + 1];
+    let mut last_item = vec![None; capacity + 1];
+    for c in 1..=capacity {
+        // doesn't pack anything into the capacity left over
+        values[c] = values[c - 1];
+        last_item[c] = None;
+        for (i, item) in items.iter().enumerate() {
+            let weight = item.weight as usize;
+            if weight <= c && values[c - weight] + item.value > values[c] {
+                values[c] = values[c - weight] + item.value;
+                last_item[c] = Some(i);
+            }
+        }
+    }
+
+    let mut counts = vec![0; items.len()];
+    let mut c = capacity;
+    while c > 0 {
+        match last_item[c] {
+            Some(i) => {
+                counts[i] += 1;
+                c -= items[i].weight as usize;
+            }
+            None => c -= 1,
+        }
+    }
+    Packing::from_counts(items, &counts)
+}
+
+/// Solves the bounded knapsack problem, item `i` packed at most `limits[i]` times.
+///
+/// The copies of every item are split into groups of 1, 2, 4, ... copies and the remainder,
+/// whose subsets make up every count up to the limit, and the groups are packed as a 0/1
+/// knapsack, in O(capacity * sum of log2(limit)). A limit above the copies fitting in the
+/// capacity counts as that many copies.
+pub fn knapsack_bounded(items: &[Item], limits: &[u64], capacity: u64) -> Packing {
+    assert_eq!(
+        items.len(),
+        limits.len(),
+        "Every item should have a limit of copies"
+    );
+    let mut groups = Vec::new();
+    let mut group_items = Vec::new();
+    for (i, (item, limit)) in items.iter().zip(limits).enumerate() {
+        // so the weights of the groups don't overflow
+        let fitting = capacity.checked_div(item.weight).unwrap_or(u64::MAX);
+        let mut left = cmp::min(*limit, fitting);
+        let mut size = 1u64;
+        while left > 0 {
+            let copies = cmp::min(size, left);
+            groups.push(Item {
+                value: item.value * copies,
+                weight: item.weight * copies,
+            });
+            group_items.push((i, copies));
+            left -= copies;
+            size = size.saturating_mul(2);
+        }
+    }
+
+    let mut counts = vec![0; items.len()];
+    for (group, _) in knapsack_01(&groups, capacity).items {
+        let (i, copies) = group_items[group];
+        counts[i] += copies;
+    }
+    Packing::from_counts(items, &counts)
+}
+
+/// Solves the 0/1 knapsack problem for capacities too big for a table per capacity, like the
+/// 2 million of `knapsack_big`.
+///
+/// Only the reachable (weight, value) states which no other state dominates, by weighing as
+/// much at most and being worth as much at least, are kept, sorted by weight. Adding an item
+/// merges the states with the states shifted by the item.
+///
+/// The packed items are reconstructed by divide and conquer instead of storing the states of
+/// every item: the states of both halves of the items are computed, the best pair of a state of
+/// each half fitting together gives the capacity and value of every half, and both halves are
+/// solved again for them. The memory stays linear in the number of states, at the cost of a
+/// logarithmic factor of time.
+pub fn knapsack_01_sparse(items: &[Item], capacity: u64) -> Packing {
+    let indices: Vec<usize> = (0..items.len()).collect();
+    let mut counts = vec![0; items.len()];
+    pack_sparse(items, &indices, capacity, &mut counts);
+    Packing::from_counts(items, &counts)
+}
+
+// packs the best subset of the items of the indices into the capacity, marking them in counts
+fn pack_sparse(items: &[Item], indices: &[usize], capacity: u64, counts: &mut [u64]) {
+    if let [i] = indices {
+        if items[*i].weight <= capacity && items[*i].value > 0 {
+            counts[*i] = 1;
+        }
+        return;
+    }
+    if indices.is_empty() {
+        return;
+    }
+
+    let (left, right) = indices.split_at(indices.len() / 2);
+    let left_states = pareto_states(items, left, capacity);
+    let right_states = pareto_states(items, right, capacity);
+
+    // for the left states by increasing weight, the fitting right states only get lighter
+    let mut best = (0, 0, 0);
+    let mut r = right_states.len();
+    for (left_weight, left_value) in &left_states {
+        while r > 0 && left_weight + right_states[r - 1].0 > capacity {
+            r -= 1;
+        }
+        if r == 0 {
+            break;
+        }
+        let (right_weight, right_value) = right_states[r - 1];
+        if left_value + right_value > best.0 {
+            best = (left_value + right_value, *left_weight, right_weight);
+        }
+    }
+
+    let (_, left_capacity, right_capacity) = best;
+    pack_sparse(items, left, left_capacity, counts);
+    pack_sparse(items, right, right_capacity, counts);
+}
+
+// the non dominated (weight, value) states of the subsets of the items of the indices fitting
+// into the capacity, by increasing weight and value
+fn pareto_states(items: &[Item], indices: &[usize], capacity: u64) -> Vec<(u64, u64)> {
+    let mut states = vec![(0u64, 0u64)];
+    let mut merged = Vec::new();
+    for i in indices {
+        let item = items[*i];
+        let shifted = states
+            .iter()
+            .take_while(|(weight, _)| weight + item.weight <= capacity)
+            .map(|(weight, value)| (weight + item.weight, value + item.value));
+
+        merged.clear();
+        let mut old = states.iter().copied().peekable();
+        let mut new = shifted.peekable();
+        loop {
+            let state = match (old.peek(), new.peek()) {
+                (Some(a), Some(b)) if (a.0, cmp::Reverse(a.1)) <= (b.0, cmp::Reverse(b.1)) => {
+                    old.next().unwrap()
+                }
+                (_, Some(_)) => new.next().unwrap(),
+                (Some(_), None) => old.next().unwrap(),
+                (None, None) => break,
+            };
+            // a lighter or equally heavy state came first, keep this one only if worth more
+            if merged
+                .last()
+                .is_none_or(|last: &(u64, u64)| state.1 > last.1)
+            {
+                merged.push(state);
+            }
+        }
+        std::mem::swap(&mut states, &mut merged);
+    }
+    states
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+
+    fn random_items(n: usize, max_weight: u64) -> Vec<Item> {
+        let mut rng = thread_rng();
+        (0..n)
+            .map(|_| Item {
+                value: rng.gen_range(0..100),
+                weight: rng.gen_range(0..=max_weight),
+            })
+            .collect()
+    }
+
+    // tries every subset of the items
+    fn brute_force_01(items: &[Item], capacity: u64) -> u64 {
+        (0u32..1 << items.len())
+            .filter_map(|set| {
+                let packed = (0..items.len()).filter(|i| set & (1 << i) != 0);
+                let (weight, value) = packed.fold((0, 0), |(weight, value), i| {
+                    (weight + items[i].weight, value + items[i].value)
+                });
+                (weight <= capacity).then_some(value)
+            })
+            .max()
+            .unwrap()
+    }
+
+    fn assert_valid(packing: &Packing, items: &[Item], capacity: u64) {
+        let counts: Vec<u64> = (0..items.len())
+            .map(|i| {
+                packing
+                    .items
+                    .iter()
+                    .find(|(j, _)| *j == i)
+                    .map_or(0, |(_, count)| *count)
+            })
+            .collect();
+        assert_eq!(*packing, Packing::from_counts(items, &counts));
+        assert!(packing.weight <= capacity);
+    }
+
+    #[test]
+    fn test_knapsack_01() {
+        // the example of the lectures
+        let items = [
+            Item {
+                value: 3,
+                weight: 4,
+            },
+            Item {
+                value: 2,
+                weight: 3,
+            },
+            Item {
+                value: 4,
+                weight: 2,
+            },
+            Item {
+                value: 4,
+                weight: 3,
+            },
+        ];
+        let packing = knapsack_01(&items, 6);
+        assert_eq!(packing.value, 8);
+        assert_eq!(packing.items, vec![(2, 1), (3, 1)]);
+        assert_eq!(knapsack_01_sparse(&items, 6), packing);
+        assert_eq!(knapsack_01(&items, 0), Packing::default());
+
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            let items = random_items(rng.gen_range(0..12), 20);
+            let capacity = rng.gen_range(0..100);
+            let expected = brute_force_01(&items, capacity);
+            for packing in [
+                knapsack_01(&items, capacity),
+                knapsack_01_sparse(&items, capacity),
+            ] {
+                assert_eq!(packing.value, expected);
+                assert_valid(&packing, &items, capacity);
+                assert!(packing.items.iter().all(|(_, count)| *count == 1));
+            }
+        }
+    }
+
+    #[test]
+    fn test_knapsack_01_sparse_big_capacity() {
+        // capacities beyond a table, weights big enough to keep few states
+        let mut rng = thread_rng();
+        let items: Vec<Item> = (0..300)
+            .map(|_| Item {
+                value: rng.gen_range(1..100_000),
+                weight: rng.gen_range(1..1_000_000),
+            })
+            .collect();
+        let capacity = 20_000_000;
+        let packing = knapsack_01_sparse(&items, capacity);
+        assert_valid(&packing, &items, capacity);
+        let states = pareto_states(&items, &(0..items.len()).collect::<Vec<_>>(), capacity);
+        assert_eq!(packing.value, states.last().unwrap().1);
+
+        // the same as the table for scaled down weights
+        let small: Vec<Item> = items
+            .iter()
+            .map(|item| Item {
+                value: item.value,
+                weight: item.weight / 10_000,
+            })
+            .collect();
+        assert_eq!(
+            knapsack_01_sparse(&small, 2000).value,
+            knapsack_01(&small, 2000).value
+        );
+    }
+
+    #[test]
+    fn test_knapsack_unbounded_and_bounded() {
+        let items = [
+            Item {
+                value: 10,
+                weight: 5,
+            },
+            Item {
+                value: 7,
+                weight: 3,
+            },
+            Item {
+                value: 1,
+                weight: 1,
+            },
+        ];
+        let packing = knapsack_unbounded(&items, 11);
+        assert_eq!(packing.value, 24);
+        assert_eq!(packing.items, vec![(0, 1), (1, 2)]);
+
+        let packing = knapsack_bounded(&items, &[0, 2, 5], 11);
+        assert_eq!(packing.value, 19);
+        assert_eq!(packing.items, vec![(1, 2), (2, 5)]);
+        let packing = knapsack_bounded(&items, &[u64::MAX; 3], 11);
+        assert_eq!(packing.value, 24);
+        assert_eq!(packing.items, vec![(0, 1), (1, 2)]);
+        let free = Item {
+            value: 0,
+            weight: 0,
+        };
+        let packing = knapsack_bounded(&[items[0], free], &[u64::MAX; 2], 11);
+        assert_eq!(packing.value, 20);
+        assert_eq!(packing.items, vec![(0, 2)]);
+
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            let items = random_items(rng.gen_range(0..5), 15);
+            let limits: Vec<u64> = items.iter().map(|_| rng.gen_range(0..4)).collect();
+            let capacity = rng.gen_range(0..60);
+
+            // every copy as an item of its own
+            let copies: Vec<Item> = items
+                .iter()
+                .zip(&limits)
+                .flat_map(|(item, limit)| std::iter::repeat_n(*item, *limit as usize))
+                .collect();
+            let packing = knapsack_bounded(&items, &limits, capacity);
+            assert_eq!(packing.value, brute_force_01(&copies, capacity));
+            assert_valid(&packing, &items, capacity);
+            assert!(packing.items.iter().all(|(i, count)| *count <= limits[*i]));
+
+            let weighing: Vec<Item> = items.into_iter().filter(|item| item.weight > 0).collect();
+            let packing = knapsack_unbounded(&weighing, capacity);
+            let limits: Vec<u64> = weighing.iter().map(|item| capacity / item.weight).collect();
+            assert_eq!(
+                packing.value,
+                knapsack_bounded(&weighing, &limits, capacity).value
+            );
+            assert_valid(&packing, &weighing, capacity);
+        }
+    }
+}
@@ -1,12 +1,30 @@
+pub mod adaptive_huffman;
+pub mod arithmetic_coding;
+pub mod bitstream;
 pub mod bubble_sort;
 pub mod count_inversions;
+pub mod crc32;
+pub mod csr_graph;
+pub mod diff;
+pub mod edit_distance;
+pub mod entropy;
 pub mod graph;
 pub mod graph_min_cut;
 pub mod heap;
 pub mod huffman;
+pub mod huffman_container;
+pub mod huffman_table;
 pub mod insertion_sort;
+pub mod interval_scheduling;
+pub mod knapsack;
 pub mod merge_sort;
 pub mod mwis;
+pub mod optimal_bst;
+pub mod quantile_sketch;
 pub mod quick_sort;
+pub mod rans;
 pub mod rec_mul;
+pub mod scheduling;
 pub mod selection_sort;
+pub mod sequence_alignment;
+pub mod sliding_median;
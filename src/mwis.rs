@@ -1,51 +1,629 @@
-use std::cmp;
-use std::collections::HashSet;
+use crate::graph::Graph;
+use std::collections::HashMap;
+use std::ops::{Add, Sub};
 
-pub fn compute_mwis(weights: &Vec<usize>) -> HashSet<usize> {
-    let wis = compute_weight_independent_set(weights);
-    // println!("wis={:?}", wis);
-    let mwis = reconstruct_mwis(wis, weights);
-    // println!("mwis={:?}", mwis);
-    mwis
+/// Vertex weight of an independent set problem.
+pub trait Weight: Copy + PartialOrd + Add<Output = Self> + Sub<Output = Self> {
+    const ZERO: Self;
 }
 
-fn compute_weight_independent_set(weights: &Vec<usize>) -> Vec<usize> {
-    if weights.is_empty() {
-        return vec![0];
-    }
-    if weights.len() == 1 {
-        return vec![0, weights[0]];
-    }
+macro_rules! impl_weight {
+    ($zero:expr; $($t:ty),*) => {
+        $(
+            impl Weight for $t {
+                const ZERO: Self = $zero;
+            }
+        )*
+    };
+}
 
-    let mut A = vec![0; weights.len() + 1];
-    A[0] = 0;
-    A[1] = weights[0];
-    for i in 2..=weights.len() {
-        A[i] = cmp::max(A[i - 1], A[i - 2] + weights[i - 1]);
+impl_weight!(0; u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+impl_weight!(0.0; f32, f64);
+
+/// Maximum weight independent set: the chosen vertices in increasing order and their total
+/// weight.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mwis<W> {
+    pub vertices: Vec<usize>,
+    pub total_weight: W,
+}
+
+impl<W: Weight> Mwis<W> {
+    pub fn contains(&self, vertex: usize) -> bool {
+        self.vertices.binary_search(&vertex).is_ok()
     }
+}
 
-    A
+/// Computes the maximum weight independent set of the path graph whose vertex `i` has weight
+/// `weights[i]`. Vertices of negative weight are never chosen, nor are NaN weights.
+pub fn compute_mwis<W: Weight>(weights: &[W]) -> Mwis<W> {
+    let wis = compute_weight_independent_set(weights);
+    reconstruct_mwis(&wis, weights)
 }
 
-fn reconstruct_mwis(wis: Vec<usize>, weights: &Vec<usize>) -> HashSet<usize> {
-    if wis.len() < 2 {
-        return HashSet::new();
+// wis[i] is the weight of the maximum weight independent set of the first i vertices
+fn compute_weight_independent_set<W: Weight>(weights: &[W]) -> Vec<W> {
+    let mut wis = vec![W::ZERO; weights.len() + 1];
+    for i in 1..=weights.len() {
+        let with_vertex = if i >= 2 {
+            wis[i - 2] + weights[i - 1]
+        } else {
+            weights[0]
+        };
+        wis[i] = if with_vertex > wis[i - 1] {
+            with_vertex
+        } else {
+            wis[i - 1]
+        };
     }
 
-    let mut result = HashSet::<usize>::with_capacity(wis.len() - 1);
+    wis
+}
+
+fn reconstruct_mwis<W: Weight>(wis: &[W], weights: &[W]) -> Mwis<W> {
+    let mut vertices = Vec::new();
     let mut i = weights.len();
-    while i >= 2 {
+    while i >= 1 {
         if wis[i - 1] >= wis[i] {
             i -= 1;
         } else {
-            result.insert(weights[i - 1]);
-            i -= 2;
+            vertices.push(i - 1);
+            i = i.saturating_sub(2);
+        }
+    }
+    vertices.reverse();
+
+    Mwis {
+        vertices,
+        total_weight: wis[weights.len()],
+    }
+}
+
+/// Computes the maximum weight independent set of a tree, or a forest, of the vertices
+/// `0..weights.len()` joined by `edges`, with dynamic programming over the tree rooted at its
+/// first vertex.
+///
+/// For every vertex `v` the best weight of its subtree is computed both with `v`, which
+/// excludes its children, and without `v`, which leaves every child free to take the better of
+/// its two options. The set is reconstructed from the roots down along the chosen options.
+///
+/// Panics if the edges contain a cycle.
+pub fn compute_tree_mwis<W: Weight>(weights: &[W], edges: &[(usize, usize)]) -> Mwis<W> {
+    let n = weights.len();
+    let mut adjacency = vec![Vec::new(); n];
+    for (u, v) in edges {
+        adjacency[*u].push(*v);
+        adjacency[*v].push(*u);
+    }
+
+    // vertices of every tree in DFS pre-order, so children come after their parents
+    let mut parent = vec![None; n];
+    let mut visited = vec![false; n];
+    let mut order = Vec::with_capacity(n);
+    for root in 0..n {
+        if visited[root] {
+            continue;
+        }
+        visited[root] = true;
+        let mut stack = vec![root];
+        while let Some(v) = stack.pop() {
+            order.push(v);
+            for u in &adjacency[v] {
+                if parent[v] == Some(*u) {
+                    continue;
+                }
+                assert!(!visited[*u], "The graph isn't a forest");
+                visited[*u] = true;
+                parent[*u] = Some(v);
+                stack.push(*u);
+            }
+        }
+    }
+    assert!(edges.len() < n || n == 0, "The graph isn't a forest");
+
+    // the best weight of the subtree of every vertex, with and without the vertex
+    let mut with_vertex = weights.to_vec();
+    let mut without_vertex = vec![W::ZERO; n];
+    for v in order.iter().rev() {
+        if let Some(p) = parent[*v] {
+            with_vertex[p] = with_vertex[p] + without_vertex[*v];
+            without_vertex[p] = without_vertex[p] + max(with_vertex[*v], without_vertex[*v]);
+        }
+    }
+
+    let mut chosen = vec![false; n];
+    let mut total_weight = W::ZERO;
+    for v in order {
+        let parent_chosen = parent[v].is_some_and(|p| chosen[p]);
+        if !parent_chosen && with_vertex[v] > without_vertex[v] {
+            chosen[v] = true;
+        }
+        if parent[v].is_none() {
+            total_weight = total_weight + max(with_vertex[v], without_vertex[v]);
+        }
+    }
+
+    Mwis {
+        vertices: (0..n).filter(|v| chosen[*v]).collect(),
+        total_weight,
+    }
+}
+
+fn max<W: Weight>(a: W, b: W) -> W {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+/// Computes the maximum weight independent set of `graph` exactly with branch and bound, the
+/// edges being taken as undirected. Vertices are the ids of `graph`, `weights` holds the weight
+/// of every one of them.
+///
+/// Every subproblem is the subgraph induced by the free vertices. It is first shrunk by
+/// reductions which keep an optimal set, see `BranchAndBound::reduce`, and a subgraph falling
+/// apart into connected components is solved component by component, each memoized, which
+/// keeps paths and trees polynomial. Otherwise the search branches on the vertex of the most
+/// neighbors, first taking it, which drops its neighbors, then dropping it. A branch is cut
+/// unless an upper bound of its vertices beats the best set known: the bound greedily covers
+/// them, heaviest first, by cliques, and an independent set has at most one vertex of a clique,
+/// so it weighs at most the sum of the heaviest vertex of every clique. The heaviest first
+/// greedy set is the first best set known.
+///
+/// The search is exponential in the worst case, meant for sparse graphs of up to a few hundred
+/// vertices.
+///
+/// Panics if a vertex of the graph or of its edges has no weight.
+pub fn compute_graph_mwis<W: Weight>(graph: &Graph, weights: &HashMap<usize, W>) -> Mwis<W> {
+    let mut ids: Vec<usize> = graph.vertices().iter().copied().collect();
+    ids.sort_unstable();
+    let index: HashMap<usize, usize> = ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+    let n = ids.len();
+    let vertex_weights: Vec<W> = ids
+        .iter()
+        .map(|id| *weights.get(id).expect("Every vertex should have a weight"))
+        .collect();
+
+    let mut adjacency = vec![BitSet::new(n); n];
+    let mut free = BitSet::new(n);
+    for (v, weight) in vertex_weights.iter().enumerate() {
+        // vertices which can't add weight never improve a set
+        if *weight > W::ZERO {
+            free.insert(v);
+        }
+    }
+    for (u, v) in graph.edges() {
+        let (u, v) = (
+            *index.get(u).expect("Every vertex should have a weight"),
+            *index.get(v).expect("Every vertex should have a weight"),
+        );
+        if u == v {
+            free.remove(u);
+        } else {
+            adjacency[u].insert(v);
+            adjacency[v].insert(u);
+        }
+    }
+
+    let mut by_weight: Vec<usize> = (0..n).collect();
+    by_weight.sort_by(|a, b| {
+        vertex_weights[*b]
+            .partial_cmp(&vertex_weights[*a])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut search = BranchAndBound {
+        weights: vertex_weights,
+        adjacency,
+        by_weight,
+        memo: HashMap::new(),
+    };
+    let greedy = search.greedy(free.clone());
+    let greedy_weight = greedy
+        .iter()
+        .fold(W::ZERO, |sum, v| sum + search.weights[*v]);
+    let floor = (greedy_weight > W::ZERO).then_some(greedy_weight);
+    let (mut total_weight, mut best) = search.solve(free, floor);
+    if total_weight <= greedy_weight {
+        (total_weight, best) = (greedy_weight, greedy);
+    }
+
+    let mut vertices: Vec<usize> = best.iter().map(|v| ids[*v]).collect();
+    vertices.sort_unstable();
+    Mwis {
+        vertices,
+        total_weight,
+    }
+}
+
+struct BranchAndBound<W> {
+    weights: Vec<W>,
+    adjacency: Vec<BitSet>,
+    // all the vertices, heaviest first
+    by_weight: Vec<usize>,
+    // optimal sets of the connected subgraphs solved so far
+    memo: HashMap<BitSet, (W, Vec<usize>)>,
+}
+
+impl<W: Weight> BranchAndBound<W> {
+    // The optimal set of the subgraph induced by the free vertices, provided it weighs more than
+    // `floor`. Otherwise the returned set weighs at most `floor` and may not be optimal, as
+    // branches which can't beat `floor` are cut.
+    fn solve(&mut self, mut free: BitSet, floor: Option<W>) -> (W, Vec<usize>) {
+        let (mut weight, mut set) = self.reduce(&mut free);
+        let floor = floor.and_then(|floor| (floor > weight).then(|| floor - weight));
+        let Some(v) = free
+            .iter()
+            .max_by_key(|v| self.adjacency[*v].intersection_len(&free))
+        else {
+            return (weight, set);
+        };
+
+        let components = self.components(&free);
+        if components.len() > 1 {
+            for component in components {
+                let (component_weight, component_set) = self.solve(component, None);
+                weight = weight + component_weight;
+                set.extend(component_set);
+            }
+            return (weight, set);
+        }
+        if let Some((memo_weight, memo_set)) = self.memo.get(&free) {
+            set.extend(memo_set);
+            return (weight + *memo_weight, set);
+        }
+        if floor.is_some_and(|floor| self.clique_cover_bound(&free) <= floor) {
+            return (weight, set);
+        }
+
+        let mut with_v = free.clone();
+        with_v.remove(v);
+        with_v.difference_with(&self.adjacency[v]);
+        let v_weight = self.weights[v];
+        let with_floor = floor.and_then(|floor| (floor > v_weight).then(|| floor - v_weight));
+        let (with_weight, mut with_set) = self.solve(with_v, with_floor);
+        with_set.push(v);
+        let mut best = (with_weight + v_weight, with_set);
+
+        let mut without_v = free.clone();
+        without_v.remove(v);
+        let without_floor = match floor {
+            Some(floor) if floor > best.0 => floor,
+            _ => best.0,
+        };
+        if self.clique_cover_bound(&without_v) > without_floor {
+            let without = self.solve(without_v, Some(without_floor));
+            if without.0 > best.0 {
+                best = without;
+            }
+        }
+
+        if floor.is_none_or(|floor| best.0 > floor) {
+            self.memo.insert(free, best.clone());
+        }
+        set.extend(best.1);
+        (weight + best.0, set)
+    }
+
+    // Removes free vertices which some optimal set takes or some optimal set leaves out, until
+    // none is left, returning the taken ones. A vertex is taken if it weighs at least as much as
+    // its free neighbors together, since an optimal set without it still is one after swapping
+    // them for it. A vertex is left out if a neighbor weighing at least as much has no other
+    // free neighbors than the vertex's, the same swap argument applying to the neighbor.
+    fn reduce(&self, free: &mut BitSet) -> (W, Vec<usize>) {
+        let mut weight = W::ZERO;
+        let mut set = Vec::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            let vertices: Vec<usize> = free.iter().collect();
+            for v in vertices {
+                if !free.contains(v) {
+                    continue;
+                }
+                let mut neighbors = self.adjacency[v].clone();
+                neighbors.intersect_with(free);
+                let neighbors_weight = neighbors
+                    .iter()
+                    .fold(W::ZERO, |sum, u| sum + self.weights[u]);
+                if self.weights[v] >= neighbors_weight {
+                    set.push(v);
+                    weight = weight + self.weights[v];
+                    free.remove(v);
+                    free.difference_with(&neighbors);
+                    changed = true;
+                    continue;
+                }
+
+                let dominated = neighbors.iter().any(|u| {
+                    let mut outside = self.adjacency[u].clone();
+                    outside.intersect_with(free);
+                    outside.difference_with(&neighbors);
+                    outside.remove(v);
+                    self.weights[u] >= self.weights[v] && outside.first().is_none()
+                });
+                if dominated {
+                    free.remove(v);
+                    changed = true;
+                }
+            }
+        }
+        (weight, set)
+    }
+
+    // an independent set taking the heaviest free vertices first
+    fn greedy(&self, mut free: BitSet) -> Vec<usize> {
+        let mut set = Vec::new();
+        for v in &self.by_weight {
+            if free.contains(*v) {
+                set.push(*v);
+                free.difference_with(&self.adjacency[*v]);
+            }
+        }
+        set
+    }
+
+    fn components(&self, free: &BitSet) -> Vec<BitSet> {
+        let mut remaining = free.clone();
+        let mut components = Vec::new();
+        while let Some(start) = remaining.first() {
+            let mut component = BitSet::new(free.len());
+            component.insert(start);
+            remaining.remove(start);
+            let mut stack = vec![start];
+            while let Some(u) = stack.pop() {
+                let mut neighbors = self.adjacency[u].clone();
+                neighbors.intersect_with(&remaining);
+                for w in neighbors.iter() {
+                    remaining.remove(w);
+                    component.insert(w);
+                    stack.push(w);
+                }
+            }
+            components.push(component);
+        }
+        components
+    }
+
+    fn clique_cover_bound(&self, free: &BitSet) -> W {
+        // every clique is kept as the set of vertices adjacent to all its members
+        let mut cliques: Vec<BitSet> = Vec::new();
+        let mut bound = W::ZERO;
+        for v in self.by_weight.iter().filter(|v| free.contains(**v)) {
+            match cliques.iter_mut().find(|clique| clique.contains(*v)) {
+                Some(clique) => clique.intersect_with(&self.adjacency[*v]),
+                None => {
+                    cliques.push(self.adjacency[*v].clone());
+                    bound = bound + self.weights[*v];
+                }
+            }
+        }
+        bound
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    fn new(len: usize) -> Self {
+        BitSet {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    // capacity in bits
+    fn len(&self) -> usize {
+        self.words.len() * 64
+    }
+
+    fn insert(&mut self, i: usize) {
+        self.words[i / 64] |= 1 << (i % 64);
+    }
+
+    fn remove(&mut self, i: usize) {
+        self.words[i / 64] &= !(1 << (i % 64));
+    }
+
+    fn contains(&self, i: usize) -> bool {
+        self.words[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    fn first(&self) -> Option<usize> {
+        self.words
+            .iter()
+            .position(|word| *word != 0)
+            .map(|i| i * 64 + self.words[i].trailing_zeros() as usize)
+    }
+
+    fn intersect_with(&mut self, other: &BitSet) {
+        for (word, other) in self.words.iter_mut().zip(&other.words) {
+            *word &= other;
         }
     }
 
-    if i == 1 {
-        result.insert(weights[i - 1]);
+    fn difference_with(&mut self, other: &BitSet) {
+        for (word, other) in self.words.iter_mut().zip(&other.words) {
+            *word &= !other;
+        }
+    }
+
+    fn intersection_len(&self, other: &BitSet) -> usize {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .map(|(word, other)| (word & other).count_ones() as usize)
+            .sum()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, word)| {
+            let mut word = *word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(i * 64 + bit)
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use std::collections::HashSet;
+
+    // tries every subset of the vertices
+    fn brute_force_mwis(weights: &[i64], edges: &[(usize, usize)]) -> i64 {
+        (0u32..1 << weights.len())
+            .filter(|set| {
+                edges
+                    .iter()
+                    .all(|(u, v)| set & (1 << u) == 0 || set & (1 << v) == 0)
+            })
+            .map(|set| {
+                (0..weights.len())
+                    .filter(|v| set & (1 << v) != 0)
+                    .map(|v| weights[v])
+                    .sum()
+            })
+            .max()
+            .unwrap()
+    }
+
+    fn is_independent(mwis: &Mwis<i64>, edges: &[(usize, usize)]) -> bool {
+        edges
+            .iter()
+            .all(|(u, v)| !(mwis.contains(*u) && mwis.contains(*v)))
+    }
+
+    fn to_graph(n: usize, edges: &[(usize, usize)]) -> Graph {
+        let mut adjacency_list: HashMap<usize, HashSet<usize>> = HashMap::new();
+        for (u, v) in edges {
+            adjacency_list.entry(*u).or_default().insert(*v);
+        }
+        Graph::from(adjacency_list, (0..n).collect(), edges.to_vec())
     }
 
-    result
+    #[test]
+    fn test_compute_tree_mwis() {
+        //     0
+        //   / | \
+        //  1  2  3
+        //  |     |
+        //  4     5
+        let edges = [(0, 1), (0, 2), (0, 3), (1, 4), (3, 5)];
+        let mwis = compute_tree_mwis(&[10, 3, 2, 4, 5, 1], &edges);
+        assert_eq!(mwis.vertices, vec![0, 4, 5]);
+        assert_eq!(mwis.total_weight, 16);
+        let mwis = compute_tree_mwis(&[5, 3, 2, 4, 5, 1], &edges);
+        assert_eq!(mwis.vertices, vec![2, 3, 4]);
+        assert_eq!(mwis.total_weight, 11);
+
+        let mut rng = thread_rng();
+        for _ in 0..200 {
+            let n = rng.gen_range(1..12);
+            let weights: Vec<i64> = (0..n).map(|_| rng.gen_range(-5..20)).collect();
+            // a random forest, every vertex attached to an earlier one or to none
+            let mut edges = Vec::new();
+            for v in 1..n {
+                if rng.gen_bool(0.8) {
+                    edges.push((rng.gen_range(0..v), v));
+                }
+            }
+            let mwis = compute_tree_mwis(&weights, &edges);
+            assert_eq!(mwis.total_weight, brute_force_mwis(&weights, &edges));
+            assert!(is_independent(&mwis, &edges));
+            let sum: i64 = mwis.vertices.iter().map(|v| weights[*v]).sum();
+            assert_eq!(sum, mwis.total_weight);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "The graph isn't a forest")]
+    fn test_compute_tree_mwis_panic_cycle() {
+        compute_tree_mwis(&[1, 1, 1], &[(0, 1), (1, 2), (2, 0)]);
+    }
+
+    #[test]
+    fn test_compute_graph_mwis() {
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            let n = rng.gen_range(1..14);
+            let weights: Vec<i64> = (0..n).map(|_| rng.gen_range(-5..20)).collect();
+            let mut edges = Vec::new();
+            for u in 0..n {
+                for v in u..n {
+                    if rng.gen_bool(0.3) {
+                        edges.push((u, v));
+                    }
+                }
+            }
+            let weight_map: HashMap<usize, i64> = weights.iter().copied().enumerate().collect();
+            let mwis = compute_graph_mwis(&to_graph(n, &edges), &weight_map);
+            assert_eq!(mwis.total_weight, brute_force_mwis(&weights, &edges));
+            assert!(is_independent(&mwis, &edges));
+            let sum: i64 = mwis.vertices.iter().map(|v| weights[*v]).sum();
+            assert_eq!(sum, mwis.total_weight);
+        }
+
+        // agrees with the dynamic programs on paths and trees of hundreds of vertices
+        let n = 300;
+        let weights: Vec<i64> = (0..n).map(|_| rng.gen_range(1..100)).collect();
+        let weight_map: HashMap<usize, i64> = weights.iter().copied().enumerate().collect();
+        let path: Vec<(usize, usize)> = (1..n).map(|v| (v - 1, v)).collect();
+        let mwis = compute_graph_mwis(&to_graph(n, &path), &weight_map);
+        assert_eq!(mwis.total_weight, compute_mwis(&weights).total_weight);
+        assert!(is_independent(&mwis, &path));
+        let tree: Vec<(usize, usize)> = (1..n).map(|v| (rng.gen_range(0..v), v)).collect();
+        let mwis = compute_graph_mwis(&to_graph(n, &tree), &weight_map);
+        assert_eq!(
+            mwis.total_weight,
+            compute_tree_mwis(&weights, &tree).total_weight
+        );
+    }
+
+    #[test]
+    fn test_compute_mwis() {
+        let mwis = compute_mwis(&[1usize, 4, 5, 4]);
+        assert_eq!(mwis.vertices, vec![1, 3]);
+        assert_eq!(mwis.total_weight, 8);
+        assert!(mwis.contains(3) && !mwis.contains(2));
+
+        // equal weights don't collapse
+        let mwis = compute_mwis(&[3u32, 1, 3, 1, 3]);
+        assert_eq!(mwis.vertices, vec![0, 2, 4]);
+        assert_eq!(mwis.total_weight, 9);
+
+        assert_eq!(
+            compute_mwis::<i32>(&[]),
+            Mwis {
+                vertices: vec![],
+                total_weight: 0
+            }
+        );
+        assert_eq!(compute_mwis(&[7u8]).vertices, vec![0]);
+    }
+
+    #[test]
+    fn test_compute_mwis_signed_and_float() {
+        let mwis = compute_mwis(&[-1i64, -5, -2]);
+        assert!(mwis.vertices.is_empty());
+        assert_eq!(mwis.total_weight, 0);
+
+        let mwis = compute_mwis(&[2, -3, 4, -1, -1, 5]);
+        assert_eq!(mwis.vertices, vec![0, 2, 5]);
+        assert_eq!(mwis.total_weight, 11);
+
+        let mwis = compute_mwis(&[0.5, 1.25, 0.5, f64::NAN, 0.75]);
+        assert_eq!(mwis.vertices, vec![1, 4]);
+        assert_eq!(mwis.total_weight, 2.0);
+    }
 }
@@ -0,0 +1,133 @@
+use crate::huffman::BinaryTree;
+use crate::mwis::Weight;
+
+/// Binary search tree of the keys minimizing the expected search cost: the tree of the key
+/// indices, in sorted order, and the sum of the frequency times the depth of every key, the
+/// root being at depth 1. The tree is `None` for no keys.
+#[derive(Debug, Clone)]
+pub struct OptimalBst<W> {
+    pub tree: Option<BinaryTree<usize>>,
+    pub cost: W,
+}
+
+/// Computes the optimal binary search tree of sorted keys accessed with `frequencies`.
+///
+/// The cost of the keys `i..j` is their total frequency, every key being a level deeper below
+/// the root, plus the costs of both subtrees of the best root. The best root of `i..j` is
+/// between the best roots of `i..j - 1` and `i + 1..j` (Knuth), which brings the dynamic
+/// program down to O(n²) time, and space.
+pub fn optimal_bst<W: Weight>(frequencies: &[W]) -> OptimalBst<W> {
+    let n = frequencies.len();
+    let mut prefix_sums = vec![W::ZERO; n + 1];
+    for (i, frequency) in frequencies.iter().enumerate() {
+        prefix_sums[i + 1] = prefix_sums[i] + *frequency;
+    }
+
+    // costs and roots of the keys i..j at i * (n + 1) + j, empty ranges costing nothing
+    let width = n + 1;
+    let mut costs = vec![W::ZERO; width * width];
+    let mut roots = vec![0; width * width];
+    for len in 1..=n {
+        for i in 0..=n - len {
+            let j = i + len;
+            let (first, last) = if len == 1 {
+                (i, i)
+            } else {
+                (roots[i * width + j - 1], roots[(i + 1) * width + j])
+            };
+            let mut best = first;
+            let mut best_cost = costs[i * width + first] + costs[(first + 1) * width + j];
+            for root in first + 1..=last {
+                let cost = costs[i * width + root] + costs[(root + 1) * width + j];
+                if cost < best_cost {
+                    best = root;
+                    best_cost = cost;
+                }
+            }
+            roots[i * width + j] = best;
+            costs[i * width + j] = best_cost + (prefix_sums[j] - prefix_sums[i]);
+        }
+    }
+
+    OptimalBst {
+        tree: build_tree(&roots, width, 0, n).map(|tree| *tree),
+        cost: costs[n],
+    }
+}
+
+fn build_tree(roots: &[usize], width: usize, i: usize, j: usize) -> Option<Box<BinaryTree<usize>>> {
+    if i == j {
+        return None;
+    }
+    let root = roots[i * width + j];
+    Some(Box::new(BinaryTree {
+        value: root,
+        left: build_tree(roots, width, i, root),
+        right: build_tree(roots, width, root + 1, j),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+
+    fn search_cost(tree: &BinaryTree<usize>, frequencies: &[u64], depth: u64) -> u64 {
+        frequencies[tree.value] * depth
+            + [&tree.left, &tree.right]
+                .into_iter()
+                .flatten()
+                .map(|child| search_cost(child, frequencies, depth + 1))
+                .sum::<u64>()
+    }
+
+    // tries every root of every range
+    fn brute_force_cost(frequencies: &[u64]) -> u64 {
+        if frequencies.is_empty() {
+            return 0;
+        }
+        let total: u64 = frequencies.iter().sum();
+        (0..frequencies.len())
+            .map(|root| {
+                brute_force_cost(&frequencies[..root]) + brute_force_cost(&frequencies[root + 1..])
+            })
+            .min()
+            .unwrap()
+            + total
+    }
+
+    #[test]
+    fn test_optimal_bst() {
+        let bst = optimal_bst(&[34u64, 8, 50]);
+        assert_eq!(bst.cost, 142);
+        let tree = bst.tree.unwrap();
+        assert_eq!(tree.value, 2);
+        assert_eq!(tree.in_order().copied().collect::<Vec<_>>(), [0, 1, 2]);
+
+        assert_eq!(optimal_bst(&[4u64, 2, 6, 3]).cost, 26);
+        assert_eq!(optimal_bst::<u64>(&[]).cost, 0);
+        assert!(optimal_bst::<u64>(&[]).tree.is_none());
+
+        // probabilities of the lecture quiz
+        let bst = optimal_bst(&[0.2f64, 0.05, 0.17, 0.1, 0.2, 0.03, 0.25]);
+        assert!((bst.cost - 2.23).abs() < 1e-9, "{}", bst.cost);
+    }
+
+    #[test]
+    fn test_optimal_bst_brute_force() {
+        let mut rng = thread_rng();
+        for _ in 0..200 {
+            let frequencies: Vec<u64> = (0..rng.gen_range(1..9))
+                .map(|_| rng.gen_range(0..100))
+                .collect();
+            let bst = optimal_bst(&frequencies);
+            let tree = bst.tree.unwrap();
+            assert_eq!(bst.cost, brute_force_cost(&frequencies));
+            assert_eq!(search_cost(&tree, &frequencies, 1), bst.cost);
+            assert_eq!(
+                tree.in_order().copied().collect::<Vec<_>>(),
+                (0..frequencies.len()).collect::<Vec<_>>()
+            );
+        }
+    }
+}
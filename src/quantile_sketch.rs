@@ -0,0 +1,512 @@
+use std::cmp::Ordering;
+use std::f64::consts::PI;
+
+#[derive(Debug, Clone)]
+struct GkTuple<T> {
+    value: T,
+    // rmin(i) - rmin(i - 1)
+    g: usize,
+    // rmax(i) - rmin(i)
+    delta: usize,
+}
+
+/// Greenwald–Khanna quantile summary.
+///
+/// Keeps a sorted list of tuples `(value, g, delta)`, where the sum of `g` up to a tuple is the
+/// smallest rank `rmin` its value can have in the stream, and `rmin + delta` is the biggest one.
+/// The summary maintains `g + delta <= 2 * epsilon * n` for every tuple, so for any quantile
+/// `phi` it answers with a value whose rank is within `epsilon * n` of `phi * n` using
+/// `O(1 / epsilon * log(epsilon * n))` tuples.
+///
+/// Merging two summaries keeps the guarantee of the bigger of the two epsilons over the
+/// combined number of values.
+#[derive(Debug, Clone)]
+pub struct GreenwaldKhanna<T: PartialOrd + Clone> {
+    epsilon: f64,
+    count: usize,
+    tuples: Vec<GkTuple<T>>,
+}
+
+impl<T: PartialOrd + Clone> GreenwaldKhanna<T> {
+    pub fn new(epsilon: f64) -> Self {
+        assert!(
+            epsilon > 0.0 && epsilon < 1.0,
+            "Epsilon should be within (0, 1): {}",
+            epsilon
+        );
+        GreenwaldKhanna {
+            epsilon,
+            count: 0,
+            tuples: Vec::new(),
+        }
+    }
+
+    pub fn epsilon(&self) -> f64 {
+        self.epsilon
+    }
+
+    /// Number of values inserted into the summary.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Number of tuples currently stored.
+    pub fn size(&self) -> usize {
+        self.tuples.len()
+    }
+
+    pub fn insert(&mut self, value: T) {
+        let index = self.tuples.partition_point(|tuple| tuple.value <= value);
+        // a new minimum or maximum has an exact rank, otherwise the value is somewhere between
+        // its neighbours, so its rank is as uncertain as the rank of its successor
+        let delta = if index == 0 || index == self.tuples.len() {
+            0
+        } else {
+            self.tuples[index].g + self.tuples[index].delta - 1
+        };
+        self.tuples.insert(index, GkTuple { value, g: 1, delta });
+        self.count += 1;
+
+        let compress_period = ((1.0 / (2.0 * self.epsilon)).floor() as usize).max(1);
+        if self.count.is_multiple_of(compress_period) {
+            self.compress();
+        }
+    }
+
+    /// Value whose rank is within `epsilon * n` of `phi * n`.
+    pub fn query(&self, phi: f64) -> Option<&T> {
+        if self.tuples.is_empty() {
+            return None;
+        }
+
+        let phi = phi.clamp(0.0, 1.0);
+        let rank = ((phi * self.count as f64).ceil() as usize).clamp(1, self.count) as f64;
+        let bound = self.epsilon * self.count as f64;
+
+        // pick the first tuple whose rank range is within the bound, falling back to the one
+        // with the smallest error should rounding push every tuple just outside of it
+        let mut best = 0;
+        let mut best_error = f64::MAX;
+        let mut rmin = 0;
+        for (index, tuple) in self.tuples.iter().enumerate() {
+            rmin += tuple.g;
+            let rmax = rmin + tuple.delta;
+            let error = f64::max(rank - rmin as f64, rmax as f64 - rank);
+            if error <= bound {
+                return Some(&tuple.value);
+            }
+            if error < best_error {
+                best = index;
+                best_error = error;
+            }
+        }
+
+        Some(&self.tuples[best].value)
+    }
+
+    pub fn median(&self) -> Option<&T> {
+        self.query(0.5)
+    }
+
+    /// Folds `other` into this summary.
+    pub fn merge(&mut self, other: &GreenwaldKhanna<T>) {
+        if other.tuples.is_empty() {
+            return;
+        }
+        if self.tuples.is_empty() {
+            self.epsilon = f64::max(self.epsilon, other.epsilon);
+            self.count = other.count;
+            self.tuples = other.tuples.clone();
+            return;
+        }
+
+        let left = ranks(&self.tuples);
+        let right = ranks(&other.tuples);
+        let (left_count, right_count) = (self.count, other.count);
+
+        // for equal values, values of "self" are considered smaller than values of "other"
+        let mut merged = Vec::<(T, usize, usize)>::with_capacity(left.len() + right.len());
+        let (mut i, mut j) = (0, 0);
+        while i < left.len() || j < right.len() {
+            let take_left = j == right.len() || (i < left.len() && left[i].0 <= right[j].0);
+            if take_left {
+                let (value, rmin, rmax) = &left[i];
+                // j is the index of the smallest value of "other" bigger than or equal to value
+                let other_rmin = if j == 0 { 0 } else { right[j - 1].1 };
+                let other_rmax = if j == right.len() {
+                    right_count
+                } else {
+                    right[j].2 - 1
+                };
+                merged.push((value.clone(), rmin + other_rmin, rmax + other_rmax));
+                i += 1;
+            } else {
+                let (value, rmin, rmax) = &right[j];
+                // i is the index of the smallest value of "self" strictly bigger than value
+                let other_rmin = if i == 0 { 0 } else { left[i - 1].1 };
+                let other_rmax = if i == left.len() {
+                    left_count
+                } else {
+                    left[i].2 - 1
+                };
+                merged.push((value.clone(), rmin + other_rmin, rmax + other_rmax));
+                j += 1;
+            }
+        }
+
+        let mut tuples = Vec::with_capacity(merged.len());
+        let mut previous_rmin = 0;
+        for (value, rmin, rmax) in merged {
+            tuples.push(GkTuple {
+                value,
+                g: rmin - previous_rmin,
+                delta: rmax - rmin,
+            });
+            previous_rmin = rmin;
+        }
+
+        self.epsilon = f64::max(self.epsilon, other.epsilon);
+        self.count += other.count;
+        self.tuples = tuples;
+        self.compress();
+    }
+
+    // merge neighbouring tuples while the merged tuple stays within the 2 * epsilon * n band,
+    // the first (minimum) and the last (maximum) tuples are always kept
+    fn compress(&mut self) {
+        let threshold = (2.0 * self.epsilon * self.count as f64).floor() as usize;
+        let mut index = self.tuples.len().saturating_sub(2);
+        while index >= 1 {
+            let next = &self.tuples[index + 1];
+            if self.tuples[index].g + next.g + next.delta <= threshold {
+                self.tuples[index + 1].g += self.tuples[index].g;
+                self.tuples.remove(index);
+            }
+            index -= 1;
+        }
+    }
+}
+
+// (value, rmin, rmax) for every tuple
+fn ranks<T: Clone>(tuples: &[GkTuple<T>]) -> Vec<(T, usize, usize)> {
+    let mut rmin = 0;
+    tuples
+        .iter()
+        .map(|tuple| {
+            rmin += tuple.g;
+            (tuple.value.clone(), rmin, rmin + tuple.delta)
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+/// Merging t-digest by Dunning and Ertl.
+///
+/// Clusters the values into centroids whose size is limited by the `k1` scale function
+/// `k(q) = compression / (2 * PI) * asin(2q - 1)`: every centroid spans at most one unit of `k`,
+/// so centroids near the tails are tiny and the ones around the median are big. The digest keeps
+/// at most `O(compression)` centroids.
+///
+/// Unlike [`GreenwaldKhanna`] there is no worst-case guarantee. In practice the rank error is
+/// proportional to `q * (1 - q) / compression`, i.e. about `1 / compression` of the values
+/// around the median and much less at the extreme quantiles. Merging digests doesn't noticeably
+/// change the accuracy.
+#[derive(Debug, Clone)]
+pub struct TDigest {
+    compression: f64,
+    centroids: Vec<Centroid>,
+    buffer: Vec<f64>,
+    count: usize,
+    min: f64,
+    max: f64,
+}
+
+impl TDigest {
+    pub fn new(compression: f64) -> Self {
+        assert!(
+            compression >= 10.0,
+            "Compression should be at least 10: {}",
+            compression
+        );
+        TDigest {
+            compression,
+            centroids: Vec::new(),
+            buffer: Vec::with_capacity(Self::buffer_capacity(compression)),
+            count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    fn buffer_capacity(compression: f64) -> usize {
+        (compression * 5.0) as usize
+    }
+
+    /// Number of values inserted into the digest.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn insert(&mut self, value: f64) {
+        assert!(!value.is_nan(), "NaN can't be inserted into a t-digest");
+        self.buffer.push(value);
+        self.count += 1;
+        self.min = f64::min(self.min, value);
+        self.max = f64::max(self.max, value);
+        if self.buffer.len() >= Self::buffer_capacity(self.compression) {
+            self.flush();
+        }
+    }
+
+    /// Estimate of the value at quantile `q`, interpolated between centroid means.
+    pub fn query(&mut self, q: f64) -> Option<f64> {
+        self.flush();
+        if self.centroids.is_empty() {
+            return None;
+        }
+        if self.centroids.len() == 1 {
+            return Some(self.centroids[0].mean);
+        }
+
+        let total = self.count as f64;
+        let target = q.clamp(0.0, 1.0) * total;
+
+        // the weight of a centroid is assumed to be spread evenly around its mean, so the
+        // mean sits at the middle of the centroid's cumulative weight
+        let first = self.centroids[0];
+        if target < first.weight / 2.0 {
+            return Some(self.min + (first.mean - self.min) * target / (first.weight / 2.0));
+        }
+
+        let mut cumulative = 0.0;
+        for pair in self.centroids.windows(2) {
+            let left_center = cumulative + pair[0].weight / 2.0;
+            let right_center = cumulative + pair[0].weight + pair[1].weight / 2.0;
+            if target <= right_center {
+                let fraction = (target - left_center) / (right_center - left_center);
+                return Some(pair[0].mean + (pair[1].mean - pair[0].mean) * fraction);
+            }
+            cumulative += pair[0].weight;
+        }
+
+        let last = self.centroids[self.centroids.len() - 1];
+        let fraction = (target - (total - last.weight / 2.0)) / (last.weight / 2.0);
+        Some(last.mean + (self.max - last.mean) * fraction.min(1.0))
+    }
+
+    pub fn median(&mut self) -> Option<f64> {
+        self.query(0.5)
+    }
+
+    /// Folds `other` into this digest.
+    pub fn merge(&mut self, other: &TDigest) {
+        self.buffer.extend_from_slice(&other.buffer);
+        self.centroids.extend_from_slice(&other.centroids);
+        self.count += other.count;
+        self.min = f64::min(self.min, other.min);
+        self.max = f64::max(self.max, other.max);
+        self.compress();
+    }
+
+    fn flush(&mut self) {
+        if !self.buffer.is_empty() {
+            self.compress();
+        }
+    }
+
+    // merge the buffered values with existing centroids in a single pass over sorted means
+    fn compress(&mut self) {
+        let mut centroids: Vec<Centroid> = self
+            .buffer
+            .drain(..)
+            .map(|value| Centroid {
+                mean: value,
+                weight: 1.0,
+            })
+            .collect();
+        centroids.append(&mut self.centroids);
+        if centroids.is_empty() {
+            return;
+        }
+        centroids.sort_by(|a, b| a.mean.partial_cmp(&b.mean).unwrap_or(Ordering::Equal));
+
+        let total: f64 = centroids.iter().map(|centroid| centroid.weight).sum();
+        let mut merged = Vec::with_capacity(centroids.len());
+        let mut current = centroids[0];
+        let mut weight_so_far = 0.0;
+        let mut q_limit = self.k_inverse(self.k(0.0) + 1.0);
+        for next in centroids.into_iter().skip(1) {
+            if (weight_so_far + current.weight + next.weight) / total <= q_limit {
+                let weight = current.weight + next.weight;
+                current.mean += (next.mean - current.mean) * next.weight / weight;
+                current.weight = weight;
+            } else {
+                weight_so_far += current.weight;
+                merged.push(current);
+                q_limit = self.k_inverse(self.k(weight_so_far / total) + 1.0);
+                current = next;
+            }
+        }
+        merged.push(current);
+
+        self.centroids = merged;
+    }
+
+    fn k(&self, q: f64) -> f64 {
+        self.compression / (2.0 * PI) * (2.0 * q - 1.0).asin()
+    }
+
+    fn k_inverse(&self, k: f64) -> f64 {
+        let k = k.min(self.compression / 4.0);
+        ((k * 2.0 * PI / self.compression).sin() + 1.0) / 2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heap;
+    use rand::{thread_rng, Rng};
+
+    // 1-based ranks which "value" occupies in "sorted"
+    fn rank_range(sorted: &[u64], value: u64) -> (usize, usize) {
+        let first = sorted.partition_point(|item| *item < value) + 1;
+        let last = sorted.partition_point(|item| *item <= value);
+        (first, last)
+    }
+
+    #[test]
+    fn test_greenwald_khanna_median() {
+        let mut rng = thread_rng();
+        let epsilon = 0.01;
+        let stream: Vec<u64> = (0..20_000).map(|_| rng.gen_range(0..1_000_000)).collect();
+        let exact_medians = heap::compute_median_stream(&stream);
+
+        let mut sketch = GreenwaldKhanna::new(epsilon);
+        let mut sorted = Vec::with_capacity(stream.len());
+        for (index, item) in stream.iter().enumerate() {
+            sketch.insert(*item);
+            // check every 1000th prefix against the exact median of the heap based code
+            if (index + 1) % 1000 == 0 {
+                sorted = stream[..=index].to_vec();
+                sorted.sort();
+                let n = index + 1;
+                let (exact_rank, _) = rank_range(&sorted, exact_medians[index]);
+                let (first, last) = rank_range(&sorted, *sketch.median().unwrap());
+                let allowed = (epsilon * n as f64).ceil() as usize + 1;
+                assert!(
+                    first <= exact_rank + allowed && exact_rank <= last + allowed,
+                    "n={}, exact rank={}, sketch ranks={}..={}",
+                    n,
+                    exact_rank,
+                    first,
+                    last
+                );
+            }
+        }
+        assert_eq!(sketch.len(), stream.len());
+        assert!(sketch.size() < stream.len() / 10);
+
+        let allowed = (epsilon * sorted.len() as f64).ceil() as usize;
+        let (first, _) = rank_range(&sorted, *sketch.query(0.0).unwrap());
+        assert!(first <= 1 + allowed);
+        let (_, last) = rank_range(&sorted, *sketch.query(1.0).unwrap());
+        assert!(last + allowed >= sorted.len());
+    }
+
+    #[test]
+    fn test_greenwald_khanna_merge() {
+        let mut rng = thread_rng();
+        let epsilon = 0.01;
+        let mut sketches: Vec<GreenwaldKhanna<u64>> =
+            (0..4).map(|_| GreenwaldKhanna::new(epsilon)).collect();
+        let mut all = Vec::new();
+        for (index, sketch) in sketches.iter_mut().enumerate() {
+            // differently distributed parts, so that merging has to interleave them
+            let offset = index as u64 * 1000;
+            for _ in 0..5000 {
+                let value = offset + rng.gen_range(0..3000);
+                sketch.insert(value);
+                all.push(value);
+            }
+        }
+        let mut merged = GreenwaldKhanna::new(epsilon);
+        for sketch in &sketches {
+            merged.merge(sketch);
+        }
+        all.sort();
+        assert_eq!(merged.len(), all.len());
+
+        let n = all.len() as f64;
+        for phi in [0.01, 0.1, 0.25, 0.5, 0.75, 0.9, 0.99] {
+            let rank = (phi * n).ceil() as usize;
+            let (first, last) = rank_range(&all, *merged.query(phi).unwrap());
+            let allowed = (epsilon * n).ceil() as usize + 1;
+            assert!(
+                first <= rank + allowed && rank <= last + allowed,
+                "phi={}, rank={}, sketch ranks={}..={}",
+                phi,
+                rank,
+                first,
+                last
+            );
+        }
+    }
+
+    #[test]
+    fn test_t_digest() {
+        let mut rng = thread_rng();
+        let stream: Vec<u64> = (0..50_000).map(|_| rng.gen_range(0..1_000_000)).collect();
+        let exact_medians = heap::compute_median_stream(&stream);
+        let mut sorted = stream.clone();
+        sorted.sort();
+
+        let mut digest = TDigest::new(100.0);
+        let mut halves = [TDigest::new(100.0), TDigest::new(100.0)];
+        for (index, item) in stream.iter().enumerate() {
+            digest.insert(*item as f64);
+            halves[index % 2].insert(*item as f64);
+        }
+        let [mut merged, second_half] = halves;
+        merged.merge(&second_half);
+        assert_eq!(digest.len(), stream.len());
+        assert_eq!(merged.len(), stream.len());
+        assert!(digest.centroids.len() <= 200);
+
+        // allow 1% of the values around the median, t-digest is much more precise at the tails
+        let n = stream.len();
+        let (exact_rank, _) = rank_range(&sorted, *exact_medians.last().unwrap());
+        for median in [digest.median().unwrap(), merged.median().unwrap()] {
+            let (first, last) = rank_range(&sorted, median.round() as u64);
+            assert!(
+                first <= exact_rank + n / 100 && exact_rank <= last + n / 100,
+                "exact rank={}, digest ranks={}..={}",
+                exact_rank,
+                first,
+                last
+            );
+        }
+        for q in [0.001, 0.01, 0.99, 0.999] {
+            let rank = (q * n as f64).ceil() as usize;
+            let (first, last) = rank_range(&sorted, digest.query(q).unwrap().round() as u64);
+            assert!(first <= rank + n / 1000 && rank <= last + n / 1000);
+        }
+        assert_eq!(digest.query(0.0), Some(sorted[0] as f64));
+        assert_eq!(digest.query(1.0), Some(sorted[n - 1] as f64));
+
+        assert_eq!(TDigest::new(100.0).median(), None);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::entropy::{EntropyCoder, FrequencyModel, SCALE_BITS, TOTAL};
+use crate::huffman::{DecodeError, MAX_DECODED_LEN};
+
+// lower bound of the normalized state, which stays in RANS_L..RANS_L << 8
+const RANS_L: u32 = 1 << 23;
+
+/// Range variant of asymmetric numeral systems (Duda), with a 32 bit state renormalized a byte
+/// at a time.
+///
+/// A symbol of frequency `f` and cumulative frequency `c` maps state `x` to
+/// `(x / f) * TOTAL + c + x % f`, growing it by `log2(TOTAL / f)` bits. Decoding pops symbols in
+/// reverse, so the encoder runs over the data backwards and its output is reversed, which lets
+/// the decoder run forward. Output layout: the final state, 4 bytes big endian, followed by the
+/// renormalization bytes.
+pub struct RansCoder;
+
+impl EntropyCoder for RansCoder {
+    fn name(&self) -> &'static str {
+        "rans"
+    }
+
+    fn encode(&self, model: &FrequencyModel, data: &[u8]) -> Vec<u8> {
+        let mut output = Vec::with_capacity(data.len() / 2 + 4);
+        if data.is_empty() {
+            return output;
+        }
+
+        let mut state = RANS_L;
+        for byte in data.iter().rev() {
+            let frequency = model.frequency(*byte);
+            assert!(frequency > 0, "Byte {} isn't in the model", byte);
+            // keeps the state below RANS_L << 8 after the encoding
+            let max_state = ((RANS_L >> SCALE_BITS) << 8) * frequency;
+            while state >= max_state {
+                output.push(state as u8);
+                state >>= 8;
+            }
+            state =
+                ((state / frequency) << SCALE_BITS) + state % frequency + model.cumulative(*byte);
+        }
+        output.extend_from_slice(&state.to_le_bytes());
+
+        output.reverse();
+        output
+    }
+
+    fn decode(
+        &self,
+        model: &FrequencyModel,
+        encoded: &[u8],
+        len: usize,
+    ) -> Result<Vec<u8>, DecodeError> {
+        // symbols of a single symbol model take no bits, so the input doesn't bound the length
+        if len > MAX_DECODED_LEN {
+            return Err(DecodeError::TooLong);
+        }
+        let mut data = Vec::with_capacity(std::cmp::min(len, encoded.len() * 8));
+        if len == 0 {
+            return Ok(data);
+        }
+
+        let state_bytes = encoded.get(..4).ok_or(DecodeError::UnexpectedEnd)?;
+        let mut state = u32::from_be_bytes(state_bytes.try_into().unwrap());
+        if !(RANS_L..RANS_L << 8).contains(&state) {
+            return Err(DecodeError::CorruptedData);
+        }
+        let mut bytes = encoded[4..].iter();
+        while data.len() < len {
+            let slot = state & (TOTAL - 1);
+            let byte = model.symbol(slot);
+            state = model.frequency(byte) * (state >> SCALE_BITS) + slot - model.cumulative(byte);
+            while state < RANS_L {
+                let next = bytes.next().ok_or(DecodeError::UnexpectedEnd)?;
+                state = (state << 8) | *next as u32;
+            }
+            data.push(byte);
+        }
+
+        // the encoder started from RANS_L, anything else means corrupted data
+        if state != RANS_L || bytes.next().is_some() {
+            return Err(DecodeError::CorruptedData);
+        }
+        Ok(data)
+    }
+}
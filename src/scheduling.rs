@@ -0,0 +1,249 @@
+use std::cmp::Ordering;
+
+/// Job of the weighted completion time problem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Job {
+    pub weight: u64,
+    pub length: u64,
+}
+
+/// Greedy rule ordering the jobs by a score, the highest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    /// `weight - length`, not always optimal.
+    Difference,
+    /// `weight / length`, which minimizes the weighted sum of completion times.
+    Ratio,
+}
+
+/// Order of the jobs of equal scores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TieBreak {
+    HigherWeight,
+    ShorterLength,
+    /// The order of the input, the sort being stable.
+    InputOrder,
+}
+
+/// Compares the `weight - length` differences of the jobs, exactly even past the range of
+/// `i64`.
+pub fn compare_difference(a: &Job, b: &Job) -> Ordering {
+    let difference = |job: &Job| job.weight as i128 - job.length as i128;
+    difference(a).cmp(&difference(b))
+}
+
+/// Compares the `weight / length` ratios of the jobs exactly, by cross multiplication instead
+/// of floating point division. A job of length 0 has an infinite ratio, unless its weight is
+/// 0 too: its ratio is undefined and it delays no job wherever it runs, so it ranks below all
+/// the others.
+pub fn compare_ratio(a: &Job, b: &Job) -> Ordering {
+    let empty = |job: &Job| job.weight == 0 && job.length == 0;
+    match (empty(a), empty(b)) {
+        (false, false) => {
+            (a.weight as u128 * b.length as u128).cmp(&(b.weight as u128 * a.length as u128))
+        }
+        (a_empty, b_empty) => b_empty.cmp(&a_empty),
+    }
+}
+
+/// Indices of the jobs ordered by decreasing score of the rule, ties broken by `tie_break`.
+pub fn greedy_order(jobs: &[Job], rule: Rule, tie_break: TieBreak) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..jobs.len()).collect();
+    order.sort_by(|i, j| {
+        let (a, b) = (&jobs[*i], &jobs[*j]);
+        let by_score = match rule {
+            Rule::Difference => compare_difference(b, a),
+            Rule::Ratio => compare_ratio(b, a),
+        };
+        by_score.then_with(|| match tie_break {
+            TieBreak::HigherWeight => b.weight.cmp(&a.weight),
+            TieBreak::ShorterLength => a.length.cmp(&b.length),
+            TieBreak::InputOrder => Ordering::Equal,
+        })
+    });
+    order
+}
+
+/// Sum of the weight times the completion time of every job, run in `order` from time 0.
+pub fn weighted_completion_time(jobs: &[Job], order: &[usize]) -> u128 {
+    let mut completion_time = 0u128;
+    let mut sum = 0;
+    for i in order {
+        completion_time += jobs[*i].length as u128;
+        sum += jobs[*i].weight as u128 * completion_time;
+    }
+    sum
+}
+
+/// Job of the maximum lateness problem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeadlineJob {
+    pub length: u64,
+    pub deadline: u64,
+}
+
+/// Indices of the jobs by increasing deadline, ties by input order, the schedule minimizing
+/// the maximum lateness: swapping two adjacent jobs out of deadline order never makes the
+/// later one finish less late.
+pub fn earliest_deadline_first(jobs: &[DeadlineJob]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..jobs.len()).collect();
+    order.sort_by_key(|i| jobs[*i].deadline);
+    order
+}
+
+/// Greatest lateness, completion time past the deadline, of the jobs run in `order` from time
+/// 0, or 0 if every job finishes by its deadline.
+pub fn max_lateness(jobs: &[DeadlineJob], order: &[usize]) -> u128 {
+    let mut completion_time = 0u128;
+    let mut lateness = 0;
+    for i in order {
+        completion_time += jobs[*i].length as u128;
+        lateness = lateness.max(completion_time.saturating_sub(jobs[*i].deadline as u128));
+    }
+    lateness
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+
+    fn jobs(weights_and_lengths: &[(u64, u64)]) -> Vec<Job> {
+        weights_and_lengths
+            .iter()
+            .map(|(weight, length)| Job {
+                weight: *weight,
+                length: *length,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_greedy_order() {
+        // the lecture example, where only the ratio is optimal
+        let lecture = jobs(&[(3, 5), (1, 2)]);
+        let difference = greedy_order(&lecture, Rule::Difference, TieBreak::HigherWeight);
+        let ratio = greedy_order(&lecture, Rule::Ratio, TieBreak::HigherWeight);
+        assert_eq!(difference, [1, 0]);
+        assert_eq!(ratio, [0, 1]);
+        assert_eq!(weighted_completion_time(&lecture, &difference), 23);
+        assert_eq!(weighted_completion_time(&lecture, &ratio), 22);
+
+        // equal differences and ratios
+        let tied = jobs(&[(2, 1), (4, 3), (4, 2), (8, 4)]);
+        assert_eq!(
+            greedy_order(&tied, Rule::Difference, TieBreak::HigherWeight),
+            [3, 2, 1, 0]
+        );
+        assert_eq!(
+            greedy_order(&tied, Rule::Difference, TieBreak::InputOrder),
+            [3, 2, 0, 1]
+        );
+        assert_eq!(
+            greedy_order(&tied, Rule::Ratio, TieBreak::ShorterLength),
+            [0, 2, 3, 1]
+        );
+        assert_eq!(
+            greedy_order(&tied, Rule::Ratio, TieBreak::HigherWeight),
+            [3, 2, 0, 1]
+        );
+
+        // jobs of weight and length 0 last
+        let with_empty = jobs(&[(0, 0), (3, 5), (0, 0), (1, 0), (0, 4), (1, 2)]);
+        let order = greedy_order(&with_empty, Rule::Ratio, TieBreak::InputOrder);
+        assert_eq!(order, [3, 1, 5, 4, 0, 2]);
+        assert_eq!(
+            weighted_completion_time(&with_empty, &order),
+            weighted_completion_time(&with_empty, &[0, 3, 2, 1, 5, 4])
+        );
+        let mut rng = thread_rng();
+        let mixed: Vec<Job> = (0..200)
+            .map(|_| Job {
+                weight: rng.gen_range(0..3),
+                length: rng.gen_range(0..3),
+            })
+            .collect();
+        let order = greedy_order(&mixed, Rule::Ratio, TieBreak::InputOrder);
+        assert!(order
+            .windows(2)
+            .all(|pair| compare_ratio(&mixed[pair[0]], &mixed[pair[1]]) != Ordering::Less));
+    }
+
+    #[test]
+    fn test_compare_exactly() {
+        // ratios equal as f64 but not exactly
+        let a = Job {
+            weight: u64::MAX,
+            length: u64::MAX - 1,
+        };
+        let b = Job {
+            weight: u64::MAX - 1,
+            length: u64::MAX - 2,
+        };
+        assert_eq!(
+            a.weight as f64 / a.length as f64,
+            b.weight as f64 / b.length as f64
+        );
+        assert_eq!(compare_ratio(&a, &b), Ordering::Less);
+
+        let zero_length = Job {
+            weight: 1,
+            length: 0,
+        };
+        assert_eq!(compare_ratio(&zero_length, &a), Ordering::Greater);
+        let empty = Job {
+            weight: 0,
+            length: 0,
+        };
+        let zero_weight = Job {
+            weight: 0,
+            length: 1,
+        };
+        assert_eq!(compare_ratio(&empty, &zero_weight), Ordering::Less);
+        assert_eq!(compare_ratio(&zero_length, &empty), Ordering::Greater);
+        assert_eq!(compare_ratio(&empty, &empty), Ordering::Equal);
+        assert_eq!(compare_difference(&a, &b), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_earliest_deadline_first() {
+        let jobs = [
+            DeadlineJob {
+                length: 3,
+                deadline: 6,
+            },
+            DeadlineJob {
+                length: 2,
+                deadline: 8,
+            },
+            DeadlineJob {
+                length: 1,
+                deadline: 9,
+            },
+            DeadlineJob {
+                length: 4,
+                deadline: 9,
+            },
+            DeadlineJob {
+                length: 3,
+                deadline: 14,
+            },
+            DeadlineJob {
+                length: 2,
+                deadline: 15,
+            },
+        ];
+        let order = earliest_deadline_first(&jobs);
+        assert_eq!(order, [0, 1, 2, 3, 4, 5]);
+        assert_eq!(max_lateness(&jobs, &order), 1);
+        assert_eq!(max_lateness(&jobs, &[5, 4, 3, 2, 1, 0]), 9);
+        assert_eq!(max_lateness(&jobs[..3], &[0, 1, 2]), 0);
+
+        // completion times past u64::MAX
+        let long = DeadlineJob {
+            length: u64::MAX,
+            deadline: 0,
+        };
+        assert_eq!(max_lateness(&[long; 3], &[0, 1, 2]), 3 * u64::MAX as u128);
+    }
+}
@@ -0,0 +1,302 @@
+use std::cmp;
+use std::ops::Range;
+
+/// Score of an aligned pair: `match_score` for equal elements, minus `mismatch_penalty` for
+/// different ones and minus `gap_penalty` for an element aligned with a gap.
+///
+/// A `match_score` of 0 makes the global alignment the minimum total penalty alignment, of
+/// penalty `-score`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scoring {
+    pub match_score: i64,
+    pub mismatch_penalty: i64,
+    pub gap_penalty: i64,
+}
+
+impl Scoring {
+    pub fn new(match_score: i64, mismatch_penalty: i64, gap_penalty: i64) -> Self {
+        Scoring {
+            match_score,
+            mismatch_penalty,
+            gap_penalty,
+        }
+    }
+
+    fn pair<T: PartialEq>(&self, x: &T, y: &T) -> i64 {
+        if x == y {
+            self.match_score
+        } else {
+            -self.mismatch_penalty
+        }
+    }
+}
+
+/// Alignment of two sequences: its score, and both aligned sequences of the same length, `None`
+/// standing for a gap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alignment<T> {
+    pub score: i64,
+    pub a: Vec<Option<T>>,
+    pub b: Vec<Option<T>>,
+    /// The aligned parts of the sequences, the whole sequences for a global alignment.
+    pub a_range: Range<usize>,
+    pub b_range: Range<usize>,
+}
+
+impl Alignment<u8> {
+    /// Both aligned byte strings, gaps shown as `gap`.
+    pub fn to_strings(&self, gap: char) -> (String, String) {
+        let render = |aligned: &[Option<u8>]| {
+            aligned
+                .iter()
+                .map(|byte| byte.map_or(gap, char::from))
+                .collect()
+        };
+        (render(&self.a), render(&self.b))
+    }
+}
+
+/// Global alignment of the whole sequences maximizing the score (Needleman–Wunsch), in
+/// O(n * m) time and space. See `hirschberg` for long sequences.
+pub fn global_alignment<T: Copy + PartialEq>(a: &[T], b: &[T], scoring: &Scoring) -> Alignment<T> {
+    let width = b.len() + 1;
+    let mut scores = vec![0i64; (a.len() + 1) * width];
+    for i in 0..=a.len() {
+        for j in 0..=b.len() {
+            scores[i * width + j] = match (i, j) {
+                (0, _) => -(j as i64) * scoring.gap_penalty,
+                (_, 0) => -(i as i64) * scoring.gap_penalty,
+                _ => best_move(&scores, width, a, b, i, j, scoring),
+            };
+        }
+    }
+
+    trace_back(&scores, width, a, b, (a.len(), b.len()), scoring, false)
+}
+
+/// Local alignment of the best scoring parts of the sequences (Smith–Waterman), in O(n * m)
+/// time and space. The score is never negative, an empty alignment scoring 0.
+pub fn local_alignment<T: Copy + PartialEq>(a: &[T], b: &[T], scoring: &Scoring) -> Alignment<T> {
+    let width = b.len() + 1;
+    let mut scores = vec![0i64; (a.len() + 1) * width];
+    let mut end = (0, 0);
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let score = cmp::max(best_move(&scores, width, a, b, i, j, scoring), 0);
+            scores[i * width + j] = score;
+            if score > scores[end.0 * width + end.1] {
+                end = (i, j);
+            }
+        }
+    }
+
+    trace_back(&scores, width, a, b, end, scoring, true)
+}
+
+/// Global alignment like `global_alignment` in O(n + m) space (Hirschberg).
+///
+/// The first half of `a` is aligned forward and the second half backward against all of `b`,
+/// keeping a single row of scores. The split of `b` maximizing the sum of both last rows is on
+/// an optimal alignment, and both halves are aligned recursively, in O(n * m) time overall.
+pub fn hirschberg<T: Copy + PartialEq>(a: &[T], b: &[T], scoring: &Scoring) -> Alignment<T> {
+    let mut aligned_a = Vec::with_capacity(a.len() + b.len());
+    let mut aligned_b = Vec::with_capacity(a.len() + b.len());
+    align_halves(a, b, scoring, &mut aligned_a, &mut aligned_b);
+
+    let score = aligned_a
+        .iter()
+        .zip(&aligned_b)
+        .map(|pair| match pair {
+            (Some(x), Some(y)) => scoring.pair(x, y),
+            _ => -scoring.gap_penalty,
+        })
+        .sum();
+    Alignment {
+        score,
+        a: aligned_a,
+        b: aligned_b,
+        a_range: 0..a.len(),
+        b_range: 0..b.len(),
+    }
+}
+
+fn align_halves<T: Copy + PartialEq>(
+    a: &[T],
+    b: &[T],
+    scoring: &Scoring,
+    aligned_a: &mut Vec<Option<T>>,
+    aligned_b: &mut Vec<Option<T>>,
+) {
+    // a table of two rows at most
+    if a.len() <= 1 || b.is_empty() {
+        let alignment = global_alignment(a, b, scoring);
+        aligned_a.extend(alignment.a);
+        aligned_b.extend(alignment.b);
+        return;
+    }
+
+    let mid = a.len() / 2;
+    let forward = last_row(&a[..mid], b, scoring, false);
+    let backward = last_row(&a[mid..], b, scoring, true);
+    let split = (0..=b.len())
+        .max_by_key(|k| (forward[*k] + backward[b.len() - k], cmp::Reverse(*k)))
+        .unwrap();
+    align_halves(&a[..mid], &b[..split], scoring, aligned_a, aligned_b);
+    align_halves(&a[mid..], &b[split..], scoring, aligned_a, aligned_b);
+}
+
+// the last row of the global alignment scores of a against the prefixes of b, or of the
+// reversed sequences
+fn last_row<T: PartialEq>(a: &[T], b: &[T], scoring: &Scoring, reversed: bool) -> Vec<i64> {
+    let at = |s: &[T], i: usize| if reversed { s.len() - 1 - i } else { i };
+    let mut row: Vec<i64> = (0..=b.len())
+        .map(|j| -(j as i64) * scoring.gap_penalty)
+        .collect();
+    for i in 0..a.len() {
+        let mut diagonal = row[0];
+        row[0] -= scoring.gap_penalty;
+        for j in 1..=b.len() {
+            let score = cmp::max(
+                diagonal + scoring.pair(&a[at(a, i)], &b[at(b, j - 1)]),
+                cmp::max(row[j], row[j - 1]) - scoring.gap_penalty,
+            );
+            diagonal = row[j];
+            row[j] = score;
+        }
+    }
+    row
+}
+
+fn best_move<T: PartialEq>(
+    scores: &[i64],
+    width: usize,
+    a: &[T],
+    b: &[T],
+    i: usize,
+    j: usize,
+    scoring: &Scoring,
+) -> i64 {
+    let diagonal = scores[(i - 1) * width + j - 1] + scoring.pair(&a[i - 1], &b[j - 1]);
+    let up = scores[(i - 1) * width + j] - scoring.gap_penalty;
+    let left = scores[i * width + j - 1] - scoring.gap_penalty;
+    cmp::max(diagonal, cmp::max(up, left))
+}
+
+// follows the moves back from the end cell to the empty prefixes, or to a score of 0 of a
+// local alignment
+fn trace_back<T: Copy + PartialEq>(
+    scores: &[i64],
+    width: usize,
+    a: &[T],
+    b: &[T],
+    end: (usize, usize),
+    scoring: &Scoring,
+    local: bool,
+) -> Alignment<T> {
+    let mut aligned_a = Vec::new();
+    let mut aligned_b = Vec::new();
+    let (mut i, mut j) = end;
+    loop {
+        let score = scores[i * width + j];
+        if local && score == 0 {
+            break;
+        } else if i > 0
+            && j > 0
+            && score == scores[(i - 1) * width + j - 1] + scoring.pair(&a[i - 1], &b[j - 1])
+        {
+            i -= 1;
+            j -= 1;
+            aligned_a.push(Some(a[i]));
+            aligned_b.push(Some(b[j]));
+        } else if i > 0 && score == scores[(i - 1) * width + j] - scoring.gap_penalty {
+            i -= 1;
+            aligned_a.push(Some(a[i]));
+            aligned_b.push(None);
+        } else if j > 0 && score == scores[i * width + j - 1] - scoring.gap_penalty {
+            j -= 1;
+            aligned_a.push(None);
+            aligned_b.push(Some(b[j]));
+        } else {
+            break;
+        }
+    }
+    aligned_a.reverse();
+    aligned_b.reverse();
+    Alignment {
+        score: scores[end.0 * width + end.1],
+        a: aligned_a,
+        b: aligned_b,
+        a_range: i..end.0,
+        b_range: j..end.1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+
+    #[test]
+    fn test_global_alignment() {
+        // minimum penalty alignment of the lecture
+        let alignment = global_alignment(b"AGGGCT", b"AGGCA", &Scoring::new(0, 1, 1));
+        assert_eq!(alignment.score, -2);
+        assert_eq!(
+            alignment.to_strings('-'),
+            ("AGGGCT".to_string(), "A-GGCA".to_string())
+        );
+
+        let alignment = global_alignment(b"", b"abc", &Scoring::new(1, 1, 2));
+        assert_eq!(alignment.score, -6);
+        assert_eq!(
+            alignment.to_strings('-'),
+            ("---".to_string(), "abc".to_string())
+        );
+    }
+
+    #[test]
+    fn test_local_alignment() {
+        let alignment = local_alignment(b"TGTTACGG", b"GGTTGACTA", &Scoring::new(3, 3, 2));
+        assert_eq!(alignment.score, 13);
+        assert_eq!(
+            alignment.to_strings('-'),
+            ("GTT-AC".to_string(), "GTTGAC".to_string())
+        );
+        assert_eq!(alignment.a_range, 1..6);
+        assert_eq!(alignment.b_range, 1..7);
+
+        let alignment = local_alignment(b"aaa", b"bbb", &Scoring::new(1, 1, 1));
+        assert_eq!(alignment.score, 0);
+        assert!(alignment.a.is_empty());
+    }
+
+    #[test]
+    fn test_hirschberg() {
+        let mut rng = thread_rng();
+        let scoring = Scoring::new(2, 1, 2);
+        for _ in 0..100 {
+            let a: Vec<u8> = (0..rng.gen_range(0..40))
+                .map(|_| rng.gen_range(0..4))
+                .collect();
+            let b: Vec<u8> = (0..rng.gen_range(0..40))
+                .map(|_| rng.gen_range(0..4))
+                .collect();
+            let alignment = hirschberg(&a, &b, &scoring);
+            assert_eq!(alignment.score, global_alignment(&a, &b, &scoring).score);
+            assert_eq!(alignment.a.len(), alignment.b.len());
+            assert_eq!(alignment.a.iter().flatten().copied().collect::<Vec<_>>(), a);
+            assert_eq!(alignment.b.iter().flatten().copied().collect::<Vec<_>>(), b);
+        }
+
+        let a: Vec<char> = "the quick brown fox jumps over the lazy dog"
+            .chars()
+            .collect();
+        let b: Vec<char> = "a quick brown dog jumps over the lazy fox"
+            .chars()
+            .collect();
+        assert_eq!(
+            hirschberg(&a, &b, &scoring).score,
+            global_alignment(&a, &b, &scoring).score
+        );
+    }
+}
@@ -0,0 +1,417 @@
+use crate::heap::{MaxHeap, MedianPick, MedianPolicy, MiddleElement, Midpoint, MinHeap};
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+
+/// Quantile of the last `window` values of a stream.
+///
+/// Uses the same two heaps as `heap::RunningMedian`, but the split between them is not fixed
+/// to the middle: `lower` keeps the `rank + 1` smallest values of the window, where
+/// `rank = floor(quantile * (n - 1))` for a window currently holding `n` values, so the
+/// quantile is the root of `lower`.
+///
+/// Expired values are removed lazily: they are recorded in `delayed` and only dropped once
+/// they surface at the root of one of the heaps. `lower_len` and `upper_len` count the
+/// values of each heap which are still in the window. On trending data expired values never
+/// surface, so both heaps are rebuilt from the window once they hold twice its size, which
+/// keeps the memory in O(k).
+pub struct SlidingQuantile<T: Ord + Hash + Clone> {
+    window: VecDeque<T>,
+    capacity: usize,
+    quantile: f64,
+    lower: MaxHeap<T>,
+    upper: MinHeap<T>,
+    lower_len: usize,
+    upper_len: usize,
+    delayed: HashMap<T, usize>,
+}
+
+impl<T: Ord + Hash + Clone> SlidingQuantile<T> {
+    pub fn new(window: usize, quantile: f64) -> Self {
+        assert!(window > 0, "Window size should be at least 1");
+        assert!(
+            (0.0..=1.0).contains(&quantile),
+            "Quantile should be within [0, 1]: {}",
+            quantile
+        );
+
+        SlidingQuantile {
+            window: VecDeque::with_capacity(window),
+            capacity: window,
+            quantile,
+            lower: MaxHeap::new(),
+            upper: MinHeap::new(),
+            lower_len: 0,
+            upper_len: 0,
+            delayed: HashMap::new(),
+        }
+    }
+
+    /// Adds `value` to the window in O(log k) amortized and returns the value which expired
+    /// from the window because of it, if any.
+    pub fn push(&mut self, value: T) -> Option<T> {
+        let expired = if self.window.len() == self.capacity {
+            let expired = self.window.pop_front().unwrap();
+            self.remove(&expired);
+            Some(expired)
+        } else {
+            None
+        };
+
+        self.window.push_back(value.clone());
+        // "lower" might have been emptied by the expired value, in which case the new value
+        // has to be compared against "upper" first, so it goes there and is moved by rebalance
+        match self.lower.peek_max() {
+            Some(lower_max) if value <= *lower_max => {
+                self.lower.insert(value);
+                self.lower_len += 1;
+            }
+            _ => {
+                self.upper.insert(value);
+                self.upper_len += 1;
+            }
+        }
+
+        self.rebalance();
+        if self.lower.len() + self.upper.len() > 2 * self.capacity {
+            self.rebuild();
+        }
+        expired
+    }
+
+    /// Number of values currently in the window.
+    pub fn len(&self) -> usize {
+        self.window.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.window.is_empty()
+    }
+
+    /// `true` once `window` values were pushed.
+    pub fn is_full(&self) -> bool {
+        self.window.len() == self.capacity
+    }
+
+    /// The value of rank `floor(quantile * (n - 1))` among the `n` values of the window.
+    pub fn quantile(&self) -> Option<&T> {
+        self.lower.peek_max()
+    }
+
+    fn target_lower_len(&self) -> usize {
+        if self.window.is_empty() {
+            return 0;
+        }
+        (self.quantile * (self.window.len() - 1) as f64).floor() as usize + 1
+    }
+
+    fn remove(&mut self, value: &T) {
+        *self.delayed.entry(value.clone()).or_insert(0) += 1;
+
+        // every value of "upper" is >= every value of "lower", so a value which is not
+        // bigger than the root of "lower" is counted in "lower"
+        if self
+            .lower
+            .peek_max()
+            .is_some_and(|lower_max| value <= lower_max)
+        {
+            self.lower_len -= 1;
+            prune_max_heap(&mut self.lower, &mut self.delayed);
+        } else {
+            self.upper_len -= 1;
+            prune_min_heap(&mut self.upper, &mut self.delayed);
+        }
+    }
+
+    // drops the expired values of both heaps, every k pushes at most, so in O(log k) amortized
+    fn rebuild(&mut self) {
+        let mut values: Vec<T> = self.window.iter().cloned().collect();
+        values.sort();
+        let upper_values = values.split_off(self.lower_len);
+        self.lower = MaxHeap::new();
+        for value in values {
+            self.lower.insert(value);
+        }
+        self.upper = MinHeap::new();
+        for value in upper_values {
+            self.upper.insert(value);
+        }
+        self.delayed.clear();
+    }
+
+    fn rebalance(&mut self) {
+        let target = self.target_lower_len();
+        while self.lower_len > target {
+            let lower_max = self.lower.extract_max().unwrap();
+            self.lower_len -= 1;
+            self.upper.insert(lower_max);
+            self.upper_len += 1;
+            prune_max_heap(&mut self.lower, &mut self.delayed);
+        }
+        while self.lower_len < target {
+            let upper_min = self.upper.extract_min().unwrap();
+            self.upper_len -= 1;
+            self.lower.insert(upper_min);
+            self.lower_len += 1;
+            prune_min_heap(&mut self.upper, &mut self.delayed);
+        }
+    }
+}
+
+// drop expired values from the root of the heap until the root is a value of the window
+fn prune_max_heap<T: Ord + Hash>(heap: &mut MaxHeap<T>, delayed: &mut HashMap<T, usize>) {
+    while let Some(root) = heap.peek_max() {
+        match delayed.get_mut(root) {
+            Some(count) => {
+                *count -= 1;
+                if *count == 0 {
+                    delayed.remove(root);
+                }
+            }
+            None => break,
+        }
+        heap.extract_max();
+    }
+}
+
+fn prune_min_heap<T: Ord + Hash>(heap: &mut MinHeap<T>, delayed: &mut HashMap<T, usize>) {
+    while let Some(root) = heap.peek_min() {
+        match delayed.get_mut(root) {
+            Some(count) => {
+                *count -= 1;
+                if *count == 0 {
+                    delayed.remove(root);
+                }
+            }
+            None => break,
+        }
+        heap.extract_min();
+    }
+}
+
+/// Median of the last `window` values of a stream.
+pub struct SlidingMedian<T: Ord + Hash + Clone> {
+    inner: SlidingQuantile<T>,
+    pick: MedianPick<T>,
+}
+
+impl<T: Ord + Hash + Clone> SlidingMedian<T> {
+    pub fn new(window: usize) -> Self {
+        Self::with_policy(window, MiddleElement::Lower)
+    }
+
+    /// Sliding median reporting the given middle element, use `averaging` for the `Average`
+    /// policy.
+    pub fn with_policy(window: usize, middle: MiddleElement) -> Self {
+        SlidingMedian {
+            inner: SlidingQuantile::new(window, 0.5),
+            pick: middle.into(),
+        }
+    }
+
+    pub fn policy(&self) -> MedianPolicy {
+        self.pick.policy()
+    }
+
+    /// Adds `value` to the window and returns the value which expired because of it, if any.
+    pub fn push(&mut self, value: T) -> Option<T> {
+        self.inner.push(value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.inner.is_full()
+    }
+
+    /// The smaller middle element, or the only middle element if the window length is odd.
+    pub fn lower_median(&self) -> Option<&T> {
+        self.inner.quantile()
+    }
+
+    /// The bigger middle element, or the only middle element if the window length is odd.
+    pub fn upper_median(&self) -> Option<&T> {
+        if self.inner.len() % 2 == 1 {
+            self.inner.quantile()
+        } else {
+            // "upper" is pruned after every change, so its root is a value of the window
+            self.inner.upper.peek_min()
+        }
+    }
+
+    /// The median of the window according to the policy.
+    pub fn median(&self) -> Option<T> {
+        let lower = self.lower_median()?;
+        let upper = self.upper_median()?;
+        Some(self.pick.pick(lower, upper))
+    }
+}
+
+impl<T: Ord + Hash + Midpoint> SlidingMedian<T> {
+    /// Sliding median of the `Average` policy.
+    pub fn averaging(window: usize) -> Self {
+        SlidingMedian {
+            inner: SlidingQuantile::new(window, 0.5),
+            pick: MedianPick::Average(T::midpoint),
+        }
+    }
+
+    /// The midpoint of both middle elements whatever the policy.
+    pub fn average_median(&self) -> Option<T> {
+        Some(T::midpoint(self.lower_median()?, self.upper_median()?))
+    }
+}
+
+/// Returns the median of every full window of `window` consecutive values of `arr`.
+pub fn sliding_window_median<T: Ord + Hash + Midpoint>(
+    arr: &[T],
+    window: usize,
+    policy: MedianPolicy,
+) -> Vec<T> {
+    let mut sliding_median = match policy {
+        MedianPolicy::Lower => SlidingMedian::with_policy(window, MiddleElement::Lower),
+        MedianPolicy::Upper => SlidingMedian::with_policy(window, MiddleElement::Upper),
+        MedianPolicy::Average => SlidingMedian::averaging(window),
+    };
+    let mut medians = Vec::with_capacity(arr.len().saturating_sub(window) + 1);
+    for item in arr {
+        sliding_median.push(item.clone());
+        if sliding_median.is_full() {
+            medians.push(sliding_median.median().unwrap());
+        }
+    }
+
+    medians
+}
+
+/// Returns the quantile of every full window of `window` consecutive values of `arr`.
+pub fn sliding_window_quantile<T: Ord + Hash + Clone>(
+    arr: &[T],
+    window: usize,
+    quantile: f64,
+) -> Vec<T> {
+    let mut sliding_quantile = SlidingQuantile::new(window, quantile);
+    let mut quantiles = Vec::with_capacity(arr.len().saturating_sub(window) + 1);
+    for item in arr {
+        sliding_quantile.push(item.clone());
+        if sliding_quantile.is_full() {
+            quantiles.push(sliding_quantile.quantile().unwrap().clone());
+        }
+    }
+
+    quantiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+
+    fn naive_quantile(window: &[i32], quantile: f64) -> i32 {
+        let mut sorted = window.to_vec();
+        sorted.sort();
+        sorted[(quantile * (sorted.len() - 1) as f64).floor() as usize]
+    }
+
+    #[test]
+    fn test_sliding_window_median() {
+        let arr = [1, 3, -1, -3, 5, 3, 6, 7];
+        assert_eq!(
+            sliding_window_median(&arr, 3, MedianPolicy::Lower),
+            vec![1, -1, -1, 3, 5, 6]
+        );
+        assert_eq!(
+            sliding_window_median(&arr, 4, MedianPolicy::Lower),
+            vec![-1, -1, -1, 3, 5]
+        );
+        assert_eq!(
+            sliding_window_median(&arr, 4, MedianPolicy::Upper),
+            vec![1, 3, 3, 5, 6]
+        );
+        assert_eq!(
+            sliding_window_median(&arr, 4, MedianPolicy::Average),
+            vec![0, 1, 1, 4, 5]
+        );
+        assert_eq!(sliding_window_median(&arr, 1, MedianPolicy::Lower), arr);
+        assert_eq!(
+            sliding_window_median(&arr, 10, MedianPolicy::Lower),
+            Vec::<i32>::new()
+        );
+
+        let mut sliding_median = SlidingMedian::new(2);
+        assert_eq!(sliding_median.push(4), None);
+        assert_eq!(sliding_median.push(4), None);
+        assert_eq!(sliding_median.push(2), Some(4));
+        assert_eq!(sliding_median.lower_median(), Some(&2));
+        assert_eq!(sliding_median.upper_median(), Some(&4));
+        assert_eq!(sliding_median.average_median(), Some(3));
+        assert_eq!(
+            SlidingMedian::<u8>::averaging(2).policy(),
+            MedianPolicy::Average
+        );
+
+        let mut sliding_median = SlidingMedian::with_policy(3, MiddleElement::Upper);
+        assert_eq!(sliding_median.policy(), MedianPolicy::Upper);
+        for word in ["pear", "apple", "fig", "banana"] {
+            sliding_median.push(word.to_string());
+        }
+        // apple, banana, fig
+        assert_eq!(sliding_median.median().unwrap(), "banana");
+        sliding_median.push("cherry".to_string());
+        sliding_median.push("date".to_string());
+        // banana, cherry, date
+        assert_eq!(sliding_median.median().unwrap(), "cherry");
+    }
+
+    #[test]
+    fn test_sliding_median_trending_memory() {
+        // expired values of a rising stream stay below the root of "lower"
+        let window = 3;
+        let mut sliding_median = SlidingMedian::new(window);
+        for value in 0..100_000 {
+            sliding_median.push(value);
+            let inner = &sliding_median.inner;
+            assert!(inner.lower.len() + inner.upper.len() <= 2 * window);
+            assert!(inner.delayed.len() <= 2 * window);
+        }
+        assert_eq!(sliding_median.median(), Some(99_998));
+
+        let mut sliding_median = SlidingMedian::new(window);
+        for value in (0..100_000).rev() {
+            sliding_median.push(value);
+            let inner = &sliding_median.inner;
+            assert!(inner.lower.len() + inner.upper.len() <= 2 * window);
+        }
+        assert_eq!(sliding_median.median(), Some(1));
+    }
+
+    #[test]
+    fn test_sliding_window_quantile() {
+        let mut rng = thread_rng();
+        for _ in 0..200 {
+            let len = rng.gen_range(1..60);
+            // few distinct values, so that lazy deletion has to deal with duplicates
+            let arr: Vec<i32> = (0..len).map(|_| rng.gen_range(-5..5)).collect();
+            let window = rng.gen_range(1..=len);
+            for quantile in [0.0, 0.1, 0.25, 0.5, 0.9, 1.0] {
+                let expected: Vec<i32> = arr
+                    .windows(window)
+                    .map(|w| naive_quantile(w, quantile))
+                    .collect();
+                assert_eq!(
+                    sliding_window_quantile(&arr, window, quantile),
+                    expected,
+                    "arr={:?}, window={}, quantile={}",
+                    arr,
+                    window,
+                    quantile
+                );
+            }
+        }
+    }
+}